@@ -0,0 +1,12 @@
+-- migrations/20261018100000_create_user_sessions_table.sql
+-- Create User Sessions Table
+CREATE TABLE user_sessions(
+    session_id uuid PRIMARY KEY,
+    user_id uuid NOT NULL
+        REFERENCES users (user_id),
+    created_at timestamptz NOT NULL,
+    last_seen_at timestamptz NOT NULL,
+    user_agent TEXT NULL,
+    ip_address TEXT NULL
+);
+CREATE INDEX user_sessions_user_id_idx ON user_sessions (user_id);
@@ -0,0 +1,24 @@
+-- migrations/20261018110000_create_audit_events_table.sql
+-- Create Audit Events Table
+CREATE TABLE audit_events(
+    event_id uuid PRIMARY KEY,
+    occurred_at timestamptz NOT NULL,
+    action TEXT NOT NULL,
+    actor_user_id uuid NULL
+        REFERENCES users (user_id),
+    ip_address TEXT NULL,
+    user_agent TEXT NULL,
+    details JSONB NOT NULL
+);
+CREATE INDEX audit_events_occurred_at_idx ON audit_events (occurred_at);
+
+-- The audit log is append-only: history must not be rewritten.
+CREATE FUNCTION reject_audit_event_changes() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_are_append_only
+    BEFORE UPDATE OR DELETE ON audit_events
+    FOR EACH ROW EXECUTE FUNCTION reject_audit_event_changes();
@@ -0,0 +1,6 @@
+-- migrations/20261018120000_add_oidc_identity_to_users.sql
+-- Add OIDC Identity To Users
+-- `email` is used to link a user to their identity provider account the first
+-- time they sign in with it; from then on they are matched on `oidc_subject`.
+ALTER TABLE users ADD COLUMN email TEXT NULL UNIQUE;
+ALTER TABLE users ADD COLUMN oidc_subject TEXT NULL UNIQUE;
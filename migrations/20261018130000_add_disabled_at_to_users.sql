@@ -0,0 +1,3 @@
+-- migrations/20261018130000_add_disabled_at_to_users.sql
+-- Add Disabled At To Users
+ALTER TABLE users ADD COLUMN disabled_at timestamptz NULL;
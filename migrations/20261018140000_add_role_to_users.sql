@@ -0,0 +1,6 @@
+-- migrations/20261018140000_add_role_to_users.sql
+-- Add Role To Users
+-- Users that already exist had full control of the deployment: they become
+-- owners. Users created from now on are plain admins unless stated otherwise.
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'owner';
+ALTER TABLE users ALTER COLUMN role SET DEFAULT 'admin';
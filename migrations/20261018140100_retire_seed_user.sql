@@ -0,0 +1,17 @@
+-- migrations/20261018140100_retire_seed_user.sql
+-- Retire Seed User
+-- 20250122170827_seed_user.sql gave every deployment the same `admin` account
+-- and password. Owners are now created on first run instead (see
+-- `bootstrap.rs`). If the seeded password was never changed, the account is
+-- deleted, or disabled when it has history that must be kept.
+DELETE FROM users
+WHERE user_id = 'dc3ae6c2-d8db-405e-b677-dd1ba568e15d'
+    AND password_hash = '$argon2id$v=19$m=15000,t=2,p=1$MsDyjFIrj5wa8j2UmFuIPA$ustGZSPdhRIbFfG/xQogzyM80vIGD/sVixfYHPhgkzg'
+    AND NOT EXISTS (SELECT 1 FROM user_sessions WHERE user_id = 'dc3ae6c2-d8db-405e-b677-dd1ba568e15d')
+    AND NOT EXISTS (SELECT 1 FROM api_tokens WHERE user_id = 'dc3ae6c2-d8db-405e-b677-dd1ba568e15d')
+    AND NOT EXISTS (SELECT 1 FROM audit_events WHERE actor_user_id = 'dc3ae6c2-d8db-405e-b677-dd1ba568e15d');
+UPDATE users
+SET disabled_at = now()
+WHERE user_id = 'dc3ae6c2-d8db-405e-b677-dd1ba568e15d'
+    AND password_hash = '$argon2id$v=19$m=15000,t=2,p=1$MsDyjFIrj5wa8j2UmFuIPA$ustGZSPdhRIbFfG/xQogzyM80vIGD/sVixfYHPhgkzg'
+    AND disabled_at IS NULL;
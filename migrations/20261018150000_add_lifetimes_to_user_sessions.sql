@@ -0,0 +1,9 @@
+-- migrations/20261018150000_add_lifetimes_to_user_sessions.sql
+-- Sessions expire after configurable timeouts instead of actix-session's
+-- one-day default, so the index records when each of them runs out.
+ALTER TABLE user_sessions ADD COLUMN expires_at timestamptz NULL;
+ALTER TABLE user_sessions ADD COLUMN idle_timeout interval NULL;
+UPDATE user_sessions
+SET expires_at = created_at + interval '1 day', idle_timeout = interval '1 day';
+ALTER TABLE user_sessions ALTER COLUMN expires_at SET NOT NULL;
+ALTER TABLE user_sessions ALTER COLUMN idle_timeout SET NOT NULL;
@@ -0,0 +1,8 @@
+-- migrations/20261018160000_create_sessions_table.sql
+-- Session state, for deployments that keep sessions in Postgres rather than Redis.
+CREATE TABLE sessions(
+    session_key TEXT PRIMARY KEY,
+    state jsonb NOT NULL,
+    expires_at timestamptz NOT NULL
+);
+CREATE INDEX sessions_expires_at_idx ON sessions (expires_at);
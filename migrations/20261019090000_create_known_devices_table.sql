@@ -0,0 +1,12 @@
+-- migrations/20261019090000_create_known_devices_table.sql
+-- The devices and addresses each user has logged in from, so that logins from
+-- anywhere else can be reported to them.
+CREATE TABLE known_devices(
+    user_id uuid NOT NULL
+        REFERENCES users (user_id),
+    user_agent TEXT NOT NULL,
+    ip_address TEXT NOT NULL,
+    first_seen_at timestamptz NOT NULL,
+    last_seen_at timestamptz NOT NULL,
+    PRIMARY KEY (user_id, user_agent, ip_address)
+);
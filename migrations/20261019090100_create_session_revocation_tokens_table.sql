@@ -0,0 +1,10 @@
+-- migrations/20261019090100_create_session_revocation_tokens_table.sql
+-- Single-use links, sent in security notifications, that log a user out everywhere.
+CREATE TABLE session_revocation_tokens(
+    token_hash TEXT PRIMARY KEY,
+    user_id uuid NOT NULL
+        REFERENCES users (user_id),
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    used_at timestamptz NULL
+);
@@ -0,0 +1,225 @@
+//! src/audit.rs
+
+use actix_web::HttpRequest;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::authentication::SessionMetadata;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuditAction {
+    LoginSucceeded,
+    LoginFailed,
+    Logout,
+    PasswordChanged,
+    NewsletterPublished,
+    ApiTokenCreated,
+    ApiTokenRevoked,
+    SessionRevoked,
+    SubscriberCreated,
+    SubscriberConfirmed,
+    SubscriberDeleted,
+    UserCreated,
+    UserDisabled,
+    SettingsReloaded,
+    LogLevelRaised,
+    LogLevelReverted,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 16] = [
+        AuditAction::LoginSucceeded,
+        AuditAction::LoginFailed,
+        AuditAction::Logout,
+        AuditAction::PasswordChanged,
+        AuditAction::NewsletterPublished,
+        AuditAction::ApiTokenCreated,
+        AuditAction::ApiTokenRevoked,
+        AuditAction::SessionRevoked,
+        AuditAction::SubscriberCreated,
+        AuditAction::SubscriberConfirmed,
+        AuditAction::SubscriberDeleted,
+        AuditAction::UserCreated,
+        AuditAction::UserDisabled,
+        AuditAction::SettingsReloaded,
+        AuditAction::LogLevelRaised,
+        AuditAction::LogLevelReverted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::LoginSucceeded => "login.succeeded",
+            AuditAction::LoginFailed => "login.failed",
+            AuditAction::Logout => "logout",
+            AuditAction::PasswordChanged => "password.changed",
+            AuditAction::NewsletterPublished => "newsletter.published",
+            AuditAction::ApiTokenCreated => "api_token.created",
+            AuditAction::ApiTokenRevoked => "api_token.revoked",
+            AuditAction::SessionRevoked => "session.revoked",
+            AuditAction::SubscriberCreated => "subscriber.created",
+            AuditAction::SubscriberConfirmed => "subscriber.confirmed",
+            AuditAction::SubscriberDeleted => "subscriber.deleted",
+            AuditAction::UserCreated => "user.created",
+            AuditAction::UserDisabled => "user.disabled",
+            AuditAction::SettingsReloaded => "settings.reloaded",
+            AuditAction::LogLevelRaised => "log_level.raised",
+            AuditAction::LogLevelReverted => "log_level.reverted",
+        }
+    }
+}
+
+impl TryFrom<&str> for AuditAction {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.as_str() == s)
+            .ok_or_else(|| format!("{} is not a known audit action.", s))
+    }
+}
+
+/// A security-relevant action, about to be appended to the audit log.
+pub struct AuditEvent {
+    pub action: AuditAction,
+    pub actor: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: serde_json::Value,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, actor: Option<Uuid>, request: &HttpRequest) -> Self {
+        let metadata = SessionMetadata::from_request(request);
+        Self {
+            action,
+            actor,
+            ip_address: metadata.ip_address,
+            user_agent: metadata.user_agent,
+            details: serde_json::json!({}),
+        }
+    }
+
+    /// An event with no HTTP request behind it, e.g. triggered from the command line.
+    pub fn without_request(action: AuditAction, actor: Option<Uuid>) -> Self {
+        Self {
+            action,
+            actor,
+            ip_address: None,
+            user_agent: None,
+            details: serde_json::json!({}),
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+#[tracing::instrument(
+    name = "Record an audit event",
+    skip(executor, event),
+    fields(action = event.action.as_str())
+)]
+pub async fn record_audit_event(
+    executor: impl PgExecutor<'_>,
+    event: AuditEvent,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_events
+            (event_id, occurred_at, action, actor_user_id, ip_address, user_agent, details)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        Utc::now(),
+        event.action.as_str(),
+        event.actor,
+        event.ip_address,
+        event.user_agent,
+        event.details,
+    )
+    .execute(executor)
+    .await
+    .context("Failed to append an event to the audit log.")?;
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct AuditFilter {
+    pub action: Option<AuditAction>,
+    pub actor_username: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Serialize)]
+pub struct RecordedAuditEvent {
+    pub event_id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub action: String,
+    pub actor_username: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: serde_json::Value,
+}
+
+/// The most recent events matching `filter`, newest first.
+#[tracing::instrument(name = "Get audit events", skip(filter, pool))]
+pub async fn get_audit_events(
+    filter: &AuditFilter,
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<RecordedAuditEvent>, anyhow::Error> {
+    let events = sqlx::query_as!(
+        RecordedAuditEvent,
+        r#"
+        SELECT
+            e.event_id,
+            e.occurred_at,
+            e.action,
+            u.username AS "actor_username?",
+            e.ip_address,
+            e.user_agent,
+            e.details
+        FROM audit_events e
+        LEFT JOIN users u ON u.user_id = e.actor_user_id
+        WHERE ($1::text IS NULL OR e.action = $1)
+            AND ($2::text IS NULL OR u.username = $2)
+            AND ($3::timestamptz IS NULL OR e.occurred_at >= $3)
+            AND ($4::timestamptz IS NULL OR e.occurred_at < $4)
+        ORDER BY e.occurred_at DESC
+        LIMIT $5
+        "#,
+        filter.action.map(|a| a.as_str()),
+        filter.actor_username,
+        filter.since,
+        filter.until,
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve audit events.")?;
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AuditAction;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn actions_round_trip_through_their_string_form() {
+        for action in AuditAction::ALL {
+            assert_ok_eq!(AuditAction::try_from(action.as_str()), action);
+        }
+    }
+
+    #[test]
+    fn an_unknown_action_is_rejected() {
+        assert_err!(AuditAction::try_from("newsletter.deleted"));
+    }
+}
@@ -0,0 +1,226 @@
+//! src/authentication/api_token.rs
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::random_token;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ApiScope {
+    PublishNewsletters,
+    ReadSubscribers,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 2] = [ApiScope::PublishNewsletters, ApiScope::ReadSubscribers];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::PublishNewsletters => "newsletters:publish",
+            ApiScope::ReadSubscribers => "subscribers:read",
+        }
+    }
+}
+
+impl TryFrom<&str> for ApiScope {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| format!("{} is not a supported API scope.", s))
+    }
+}
+
+/// The scopes granted to the API token that authenticated the current request.
+#[derive(Clone, Debug)]
+pub struct ApiScopes(Vec<ApiScope>);
+
+impl ApiScopes {
+    pub fn contains(&self, scope: ApiScope) -> bool {
+        self.0.contains(&scope)
+    }
+}
+
+/// A freshly generated API token.
+///
+/// Only its SHA-256 digest is stored: the plain value is shown to the user once.
+pub struct ApiToken(Secret<String>);
+
+impl ApiToken {
+    const PREFIX: &'static str = "z2p_";
+
+    pub fn generate() -> Self {
+        Self(Secret::new(format!("{}{}", Self::PREFIX, random_token(40))))
+    }
+}
+
+impl ExposeSecret<String> for ApiToken {
+    fn expose_secret(&self) -> &String {
+        self.0.expose_secret()
+    }
+}
+
+fn hash_api_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub struct NewApiToken {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct ApiTokenSummary {
+    pub token_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Create API token", skip(new_token, pool))]
+pub async fn create_api_token(
+    user_id: Uuid,
+    new_token: NewApiToken,
+    pool: &PgPool,
+) -> Result<ApiToken, anyhow::Error> {
+    let token = ApiToken::generate();
+    let scopes: Vec<String> = new_token
+        .scopes
+        .iter()
+        .map(|s| s.as_str().to_owned())
+        .collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens
+            (token_id, user_id, name, token_hash, scopes, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        new_token.name,
+        hash_api_token(token.expose_secret()),
+        &scopes,
+        Utc::now(),
+        new_token.expires_at,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store a new API token in the database.")?;
+    Ok(token)
+}
+
+#[tracing::instrument(name = "Get active API tokens", skip(pool))]
+pub async fn get_active_api_tokens(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<ApiTokenSummary>, anyhow::Error> {
+    let tokens = sqlx::query_as!(
+        ApiTokenSummary,
+        r#"
+        SELECT token_id, name, scopes, created_at, expires_at
+        FROM api_tokens
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > now()
+        ORDER BY created_at
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the active API tokens.")?;
+    Ok(tokens)
+}
+
+/// Revoke one of `user_id`'s tokens, returning `false` if there was no such token.
+#[tracing::instrument(name = "Revoke API token", skip(pool))]
+pub async fn revoke_api_token(
+    user_id: Uuid,
+    token_id: Uuid,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE api_tokens
+        SET revoked_at = now()
+        WHERE token_id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        token_id,
+        user_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke an API token.")?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Look up the owner and scopes of a valid (unrevoked, unexpired) token
+/// belonging to an enabled user.
+#[tracing::instrument(name = "Authenticate API token", skip(token, pool))]
+pub async fn authenticate_api_token(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<(Uuid, ApiScopes)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT t.user_id, t.scopes
+        FROM api_tokens t
+        JOIN users u ON u.user_id = t.user_id
+        WHERE t.token_hash = $1
+            AND t.revoked_at IS NULL
+            AND t.expires_at > now()
+            AND u.disabled_at IS NULL
+        "#,
+        hash_api_token(token),
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve an API token.")?;
+    Ok(row.map(|row| {
+        let scopes = row
+            .scopes
+            .iter()
+            .filter_map(|s| ApiScope::try_from(s.as_str()).ok())
+            .collect();
+        (row.user_id, ApiScopes(scopes))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_api_token, ApiScope, ApiToken};
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn scopes_round_trip_through_their_string_form() {
+        for scope in ApiScope::ALL {
+            assert_ok_eq!(ApiScope::try_from(scope.as_str()), scope);
+        }
+    }
+
+    #[test]
+    fn an_unknown_scope_is_rejected() {
+        assert_err!(ApiScope::try_from("subscribers:delete"));
+    }
+
+    #[test]
+    fn generated_tokens_are_unique_and_hash_deterministically() {
+        let first = ApiToken::generate();
+        let second = ApiToken::generate();
+        assert_ne!(first.expose_secret(), second.expose_secret());
+        assert_eq!(
+            hash_api_token(first.expose_secret()),
+            hash_api_token(first.expose_secret())
+        );
+        assert_ne!(
+            hash_api_token(first.expose_secret()),
+            hash_api_token(second.expose_secret())
+        );
+    }
+}
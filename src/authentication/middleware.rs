@@ -1,15 +1,25 @@
 //! src/authentication/middleware.rs
 
 use actix_web::body::MessageBody;
-use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
 use actix_web::error::InternalError;
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::http::Method;
 use actix_web::middleware::Next;
+use actix_web::web;
 use actix_web::FromRequest;
-use actix_web::HttpMessage;
+use actix_web::{HttpMessage, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use chrono::Utc;
+use sqlx::PgPool;
 use std::ops::Deref;
 use uuid::Uuid;
 
-use crate::session_state::TypedSession;
+use crate::authentication::api_token::authenticate_api_token;
+use crate::authentication::sessions::{revoke_session, touch_session};
+use crate::authentication::users::{get_user_role, UserRole};
+use crate::configuration::SessionSettings;
+use crate::session_state::{TypedSession, SESSION_COOKIE_NAME};
 use crate::utils::{e500, see_other};
 
 #[derive(Copy, Clone, Debug)]
@@ -30,23 +40,213 @@ impl Deref for UserId {
 }
 
 pub async fn reject_anonymous_users(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse, actix_web::Error> {
+    let session = {
+        let (http_request, payload) = req.parts_mut();
+        TypedSession::from_request(http_request, payload).await
+    }?;
+
+    let user_id = session.get_user_id().map_err(e500)?;
+    let session_id = session.get_session_id().map_err(e500)?;
+    match (user_id, session_id) {
+        (Some(user_id), Some(session_id)) => {
+            let pool = connection_pool(&req)?;
+            let expires_at = session.get_expires_at().map_err(e500)?;
+            if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+                revoke_session(user_id, session_id, &pool)
+                    .await
+                    .map_err(e500)?;
+                session.log_out();
+                // Errors would bypass the flash messages middleware: respond instead.
+                FlashMessage::info("Your session has expired. Please log in again.").send();
+                return Ok(req.into_response(see_other("/login")));
+            }
+            // A session that is missing from its user's index has been revoked.
+            if touch_session(session_id, user_id, &pool)
+                .await
+                .map_err(e500)?
+            {
+                req.extensions_mut().insert(UserId(user_id));
+                next.call(req)
+                    .await
+                    .map(ServiceResponse::map_into_boxed_body)
+            } else {
+                let response = see_other("/login");
+                let e = anyhow::anyhow!("The session has been revoked");
+                Err(InternalError::from_response(e, response).into())
+            }
+        }
+        _ => {
+            // The browser still holds a session cookie, but the store has
+            // nothing left under it: the session was idle for too long.
+            if session.is_empty() && req.cookie(SESSION_COOKIE_NAME).is_some() {
+                FlashMessage::info(
+                    "You have been logged out after a period of inactivity. Please log in again.",
+                )
+                .send();
+                return Ok(req.into_response(see_other("/login")));
+            }
+            let response = see_other("/login");
+            let e = anyhow::anyhow!("The user has not logged in");
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
+
+/// Send users who have not entered their password within the reauthentication
+/// window to do so, before letting them take a sensitive action.
+///
+/// It must be registered inside `reject_anonymous_users`.
+pub async fn require_recent_authentication(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse, actix_web::Error> {
+    let session = {
+        let (http_request, payload) = req.parts_mut();
+        TypedSession::from_request(http_request, payload).await
+    }?;
+    let window = req
+        .app_data::<web::Data<SessionSettings>>()
+        .ok_or_else(|| e500("The session settings are not registered."))?
+        .reauthentication_window();
+    let authenticated_at = session.get_authenticated_at().map_err(e500)?;
+    let is_recent = authenticated_at.is_some_and(|authenticated_at| {
+        (Utc::now() - authenticated_at)
+            .to_std()
+            .is_ok_and(|elapsed| elapsed < window)
+    });
+    if is_recent {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_boxed_body);
+    }
+
+    // Come back to the page the action was taken from: form submissions
+    // cannot be replayed through a redirect.
+    let return_to = if *req.method() == Method::GET {
+        req.uri()
+            .path_and_query()
+            .map_or(req.path(), |path_and_query| path_and_query.as_str())
+    } else {
+        req.path()
+    };
+    let location = format!(
+        "/admin/reauthenticate?next={}",
+        url::form_urlencoded::byte_serialize(return_to.as_bytes()).collect::<String>()
+    );
+    FlashMessage::info("Please enter your password again to continue.").send();
+    Ok(req.into_response(see_other(&location)))
+}
+
+/// Only let owners through, to administer the deployment itself.
+///
+/// It must be registered inside `reject_anonymous_users`.
+pub async fn reject_non_owners(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse, actix_web::Error> {
+    let user_id = req
+        .extensions()
+        .get::<UserId>()
+        .copied()
+        .ok_or_else(|| e500("The user has not been authenticated."))?;
+    let pool = connection_pool(&req)?;
+    if get_user_role(*user_id, &pool).await.map_err(e500)? == UserRole::Owner {
+        next.call(req)
+            .await
+            .map(ServiceResponse::map_into_boxed_body)
+    } else {
+        let response = HttpResponse::Forbidden().body("Only owners can do this.");
+        let e = anyhow::anyhow!("The user is not an owner");
+        Err(InternalError::from_response(e, response).into())
+    }
+}
+
+/// Reject state-changing requests whose `csrf_token` form field does not match
+/// the token stored in the session, i.e. forms that were not rendered by us.
+pub async fn reject_invalid_csrf_tokens(
     mut req: ServiceRequest,
     next: Next<impl MessageBody>,
 ) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.call(req).await;
+    }
+
     let session = {
         let (http_request, payload) = req.parts_mut();
         TypedSession::from_request(http_request, payload).await
     }?;
+    let expected_token = session.get_csrf_token().map_err(e500)?;
+
+    // The handler still needs the body: put it back once we have read the token.
+    let body = req.extract::<web::Bytes>().await?;
+    let submitted_token = url::form_urlencoded::parse(&body)
+        .find(|(key, _)| key == "csrf_token")
+        .map(|(_, value)| value.into_owned());
+    req.set_payload(Payload::from(body));
+
+    match (expected_token, submitted_token) {
+        (Some(expected), Some(submitted)) if constant_time_eq(&expected, &submitted) => {
+            next.call(req).await
+        }
+        _ => {
+            let response = HttpResponse::Forbidden().body("Invalid or missing CSRF token.");
+            let e = anyhow::anyhow!("The request did not carry the session's CSRF token");
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
 
-    match session.get_user_id().map_err(e500)? {
-        Some(user_id) => {
+pub async fn reject_invalid_api_tokens(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(token) = bearer_token(req.headers()) else {
+        let e = anyhow::anyhow!("Missing bearer token.");
+        return Err(InternalError::from_response(e, unauthorized()).into());
+    };
+    let pool = connection_pool(&req)?;
+    match authenticate_api_token(&token, &pool).await.map_err(e500)? {
+        Some((user_id, scopes)) => {
             req.extensions_mut().insert(UserId(user_id));
+            req.extensions_mut().insert(scopes);
             next.call(req).await
         }
         None => {
-            let response = see_other("/login");
-            let e = anyhow::anyhow!("The user has not logged in");
-            Err(InternalError::from_response(e, response).into())
+            let e = anyhow::anyhow!("Invalid, expired or revoked API token.");
+            Err(InternalError::from_response(e, unauthorized()).into())
         }
     }
 }
+
+fn connection_pool(req: &ServiceRequest) -> Result<web::Data<PgPool>, actix_web::Error> {
+    req.app_data::<web::Data<PgPool>>()
+        .cloned()
+        .ok_or_else(|| e500("The database pool is not registered."))
+}
+
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_owned())
+}
+
+fn unauthorized() -> HttpResponse {
+    HttpResponse::Unauthorized()
+        .insert_header((header::WWW_AUTHENTICATE, r#"Bearer realm="api""#))
+        .json(serde_json::json!({ "error": "A valid API token is required." }))
+}
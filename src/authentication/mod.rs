@@ -1,5 +1,33 @@
 //! src/authentication/mod.rs
+mod api_token;
 pub mod middleware;
+mod notifications;
+mod oidc;
 mod password;
-pub use middleware::{reject_anonymous_users, UserId};
-pub use password::{change_password, validate_credentials, AuthError, Credentials};
+mod password_policy;
+mod sessions;
+mod users;
+pub use api_token::{
+    create_api_token, get_active_api_tokens, revoke_api_token, ApiScope, ApiScopes, ApiToken,
+    ApiTokenSummary, NewApiToken,
+};
+pub use middleware::{
+    reject_anonymous_users, reject_invalid_api_tokens, reject_invalid_csrf_tokens,
+    reject_non_owners, require_recent_authentication, UserId,
+};
+pub use notifications::{
+    is_valid_session_revocation_token, remember_device, revoke_sessions_with_token,
+    send_security_notification, RevokedSessions, SecurityNotification,
+};
+pub use oidc::{find_oidc_user, IdTokenClaims, OidcClient, PendingOidcLogin};
+pub use password::{
+    change_password, validate_credentials, AuthError, Credentials, PasswordHashing,
+};
+pub use password_policy::{check_password_policy, PasswordRejection};
+pub use sessions::{
+    get_active_sessions, record_session, revoke_all_sessions, revoke_other_sessions,
+    revoke_session, touch_session, ActiveSession, SessionMetadata,
+};
+pub use users::{
+    create_user, disable_user, get_user_id, get_user_role, list_users, UserRole, UserSummary,
+};
@@ -0,0 +1,256 @@
+//! src/authentication/notifications.rs
+
+use anyhow::Context;
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{revoke_all_sessions, SessionMetadata};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::utils::random_token;
+
+/// Account activity that users are told about by email, in case it wasn't them.
+#[derive(Copy, Clone, Debug)]
+pub enum SecurityNotification {
+    NewDeviceLogin,
+    PasswordChanged,
+}
+
+impl SecurityNotification {
+    fn subject(&self) -> &'static str {
+        match self {
+            SecurityNotification::NewDeviceLogin => "New login to your account",
+            SecurityNotification::PasswordChanged => "Your password has been changed",
+        }
+    }
+
+    fn summary(&self) -> &'static str {
+        match self {
+            SecurityNotification::NewDeviceLogin => {
+                "Your account was just logged into from a device or address we haven't seen before."
+            }
+            SecurityNotification::PasswordChanged => {
+                "The password of your account was just changed."
+            }
+        }
+    }
+}
+
+/// A single-use secret, sent in security notifications, that logs its user out everywhere.
+///
+/// Only its SHA-256 digest is stored.
+pub struct SessionRevocationToken(Secret<String>);
+
+impl SessionRevocationToken {
+    fn generate() -> Self {
+        Self(Secret::new(random_token(40)))
+    }
+}
+
+impl ExposeSecret<String> for SessionRevocationToken {
+    fn expose_secret(&self) -> &String {
+        self.0.expose_secret()
+    }
+}
+
+fn hash_revocation_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Add the device `metadata` describes to the ones `user_id` has logged in from.
+///
+/// Returns `true` if the user had logged in before, but never from this
+/// device and address: their very first login is not worth an alert.
+#[tracing::instrument(name = "Remember login device", skip(metadata, pool))]
+pub async fn remember_device(
+    user_id: Uuid,
+    metadata: &SessionMetadata,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let has_known_devices = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM known_devices WHERE user_id = $1) AS "exists!""#,
+        user_id,
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .context("Failed to look up known devices.")?;
+    let is_new = sqlx::query_scalar!(
+        r#"
+        INSERT INTO known_devices (user_id, user_agent, ip_address, first_seen_at, last_seen_at)
+        VALUES ($1, $2, $3, now(), now())
+        ON CONFLICT (user_id, user_agent, ip_address) DO UPDATE SET last_seen_at = now()
+        RETURNING first_seen_at = last_seen_at AS "is_new!"
+        "#,
+        user_id,
+        metadata.user_agent.as_deref().unwrap_or_default(),
+        metadata.ip_address.as_deref().unwrap_or_default(),
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .context("Failed to record a login device.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to record a login device.")?;
+    Ok(has_known_devices && is_new)
+}
+
+/// Email `user_id` about `notification`, if they have an email address.
+///
+/// The email describes where the activity came from and links to a page
+/// that logs the user out of every session.
+#[tracing::instrument(
+    name = "Send security notification",
+    skip(metadata, pool, email_client, base_url)
+)]
+pub async fn send_security_notification(
+    notification: SecurityNotification,
+    user_id: Uuid,
+    metadata: &SessionMetadata,
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+) -> Result<(), anyhow::Error> {
+    let email = sqlx::query_scalar!("SELECT email FROM users WHERE user_id = $1", user_id)
+        .fetch_one(pool)
+        .await
+        .context("Failed to retrieve the user's email address.")?;
+    let Some(email) = email else {
+        tracing::info!("The user has no email address to notify.");
+        return Ok(());
+    };
+    let recipient = SubscriberEmail::parse(email).map_err(|e| anyhow::anyhow!(e))?;
+
+    let token = create_session_revocation_token(user_id, pool).await?;
+    let revocation_link = format!(
+        "{}/account/revoke-sessions?token={}",
+        base_url,
+        token.expose_secret()
+    );
+    let time = Utc::now().format("%Y-%m-%d %H:%M UTC").to_string();
+    let ip_address = metadata.ip_address.as_deref().unwrap_or("Unknown");
+    let user_agent = metadata.user_agent.as_deref().unwrap_or("Unknown");
+
+    let plain_body = format!(
+        "{}\n\n\
+        Time: {}\n\
+        IP address: {}\n\
+        Device: {}\n\n\
+        If this was you, there is nothing to do.\n\
+        If it wasn't, visit {} to log out of every session, then change your password.",
+        notification.summary(),
+        time,
+        ip_address,
+        user_agent,
+        revocation_link,
+    );
+    let html_body = format!(
+        "<p>{}</p>\
+        <ul>\
+        <li>Time: {}</li>\
+        <li>IP address: {}</li>\
+        <li>Device: {}</li>\
+        </ul>\
+        <p>If this was you, there is nothing to do.<br />\
+        If it wasn't, click <a href=\"{}\">here</a> to log out of every session, \
+        then change your password.</p>",
+        notification.summary(),
+        time,
+        htmlescape::encode_minimal(ip_address),
+        htmlescape::encode_minimal(user_agent),
+        revocation_link,
+    );
+    email_client
+        .send_email(&recipient, notification.subject(), &html_body, &plain_body)
+        .await
+        .context("Failed to send a security notification.")?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Create session revocation token", skip(pool))]
+async fn create_session_revocation_token(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<SessionRevocationToken, anyhow::Error> {
+    let token = SessionRevocationToken::generate();
+    sqlx::query!(
+        r#"
+        INSERT INTO session_revocation_tokens (token_hash, user_id, created_at, expires_at)
+        VALUES ($1, $2, now(), now() + interval '7 days')
+        "#,
+        hash_revocation_token(token.expose_secret()),
+        user_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store a session revocation token.")?;
+    Ok(token)
+}
+
+/// Whether `token` can still be used to revoke sessions.
+#[tracing::instrument(name = "Check session revocation token", skip(token, pool))]
+pub async fn is_valid_session_revocation_token(
+    token: &str,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let is_valid = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM session_revocation_tokens
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        ) AS "exists!"
+        "#,
+        hash_revocation_token(token),
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to look up a session revocation token.")?;
+    Ok(is_valid)
+}
+
+pub struct RevokedSessions {
+    pub user_id: Uuid,
+    pub revoked: u64,
+}
+
+/// Spend `token` to revoke every session of its user.
+///
+/// Returns `None` if the token is unknown, expired or already used.
+#[tracing::instrument(name = "Revoke sessions with token", skip(token, pool))]
+pub async fn revoke_sessions_with_token(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<RevokedSessions>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let user_id = sqlx::query_scalar!(
+        r#"
+        UPDATE session_revocation_tokens
+        SET used_at = now()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        RETURNING user_id
+        "#,
+        hash_revocation_token(token),
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to use a session revocation token.")?;
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+    let revoked = revoke_all_sessions(user_id, &mut *transaction).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to revoke sessions.")?;
+    Ok(Some(RevokedSessions { user_id, revoked }))
+}
@@ -0,0 +1,282 @@
+//! src/authentication/oidc.rs
+
+use anyhow::Context;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use reqwest::{Client, StatusCode, Url};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::middleware::constant_time_eq;
+use super::AuthError;
+use crate::configuration::OidcSettings;
+use crate::utils::random_token;
+
+/// Signature algorithms we accept on ID tokens.
+///
+/// Symmetric algorithms are left out on purpose: the key would be our client
+/// secret, and anyone holding it could mint tokens for any user.
+const ALLOWED_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// Signs admins in through an OpenID Connect provider, using the
+/// authorization code flow with PKCE.
+///
+/// The provider metadata and its signing keys are fetched on every sign-in:
+/// logins are rare enough that caching them is not worth going stale for.
+pub struct OidcClient {
+    http_client: Client,
+    issuer_url: String,
+    client_id: String,
+    client_secret: Secret<String>,
+    redirect_url: String,
+}
+
+/// What we need to remember, in the session, between sending the user to the
+/// identity provider and getting them back.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PendingOidcLogin {
+    state: String,
+    nonce: String,
+    pkce_verifier: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    nonce: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+    jwks_uri: Url,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+impl OidcClient {
+    pub fn new(settings: OidcSettings, base_url: &str, timeout: std::time::Duration) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            http_client,
+            issuer_url: settings.issuer_url.trim_end_matches('/').to_owned(),
+            client_id: settings.client_id,
+            client_secret: settings.client_secret,
+            redirect_url: format!("{}/login/oidc/callback", base_url),
+        }
+    }
+
+    /// Where to send the user to sign in, along with the secrets the callback
+    /// has to be checked against.
+    #[tracing::instrument(name = "Build an OIDC authorization request", skip(self))]
+    pub async fn authorization_request(&self) -> Result<(Url, PendingOidcLogin), anyhow::Error> {
+        let metadata = self.provider_metadata().await?;
+        let pending = PendingOidcLogin {
+            state: random_token(32),
+            nonce: random_token(32),
+            pkce_verifier: random_token(64),
+        };
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(&pending.pkce_verifier));
+        let mut url = metadata.authorization_endpoint;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_url)
+            .append_pair("scope", "openid email")
+            .append_pair("state", &pending.state)
+            .append_pair("nonce", &pending.nonce)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+        Ok((url, pending))
+    }
+
+    /// Redeem the authorization code the provider sent the user back with and
+    /// return the claims of the validated ID token.
+    #[tracing::instrument(name = "Complete an OIDC sign-in", skip_all)]
+    pub async fn exchange_code(
+        &self,
+        pending: PendingOidcLogin,
+        state: &str,
+        code: &str,
+    ) -> Result<IdTokenClaims, AuthError> {
+        if !constant_time_eq(&pending.state, state) {
+            return Err(AuthError::InvalidCredentials(anyhow::anyhow!(
+                "The state returned by the identity provider does not match."
+            )));
+        }
+        let metadata = self.provider_metadata().await?;
+
+        let response = self
+            .http_client
+            .post(metadata.token_endpoint)
+            .basic_auth(&self.client_id, Some(self.client_secret.expose_secret()))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.redirect_url),
+                ("code_verifier", &pending.pkce_verifier),
+            ])
+            .send()
+            .await
+            .context("Failed to reach the token endpoint.")?;
+        if response.status() == StatusCode::BAD_REQUEST {
+            return Err(AuthError::InvalidCredentials(anyhow::anyhow!(
+                "The identity provider refused the authorization code."
+            )));
+        }
+        let TokenResponse { id_token } = response
+            .error_for_status()
+            .context("The token endpoint returned an error.")?
+            .json()
+            .await
+            .context("Failed to parse the token endpoint response.")?;
+
+        let jwks: JwkSet = self
+            .http_client
+            .get(metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .context("Failed to fetch the identity provider signing keys.")?
+            .json()
+            .await
+            .context("Failed to parse the identity provider signing keys.")?;
+
+        let claims = self
+            .validate_id_token(&id_token, &metadata.issuer, &jwks)
+            .map_err(AuthError::InvalidCredentials)?;
+        match &claims.nonce {
+            Some(nonce) if constant_time_eq(nonce, &pending.nonce) => Ok(claims),
+            _ => Err(AuthError::InvalidCredentials(anyhow::anyhow!(
+                "The ID token nonce does not match."
+            ))),
+        }
+    }
+
+    fn validate_id_token(
+        &self,
+        id_token: &str,
+        issuer: &str,
+        jwks: &JwkSet,
+    ) -> Result<IdTokenClaims, anyhow::Error> {
+        let header = jsonwebtoken::decode_header(id_token).context("Malformed ID token.")?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            anyhow::bail!("ID tokens signed with {:?} are not accepted.", header.alg);
+        }
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .context("The ID token was not signed with any of the provider's keys.")?;
+        let key = DecodingKey::from_jwk(jwk).context("Unusable identity provider key.")?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[&self.client_id]);
+        let token = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .context("Invalid ID token.")?;
+        Ok(token.claims)
+    }
+
+    async fn provider_metadata(&self) -> Result<ProviderMetadata, anyhow::Error> {
+        let url = format!("{}/.well-known/openid-configuration", self.issuer_url);
+        let metadata: ProviderMetadata = self
+            .http_client
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .context("Failed to fetch the identity provider metadata.")?
+            .json()
+            .await
+            .context("Failed to parse the identity provider metadata.")?;
+        if metadata.issuer.trim_end_matches('/') != self.issuer_url {
+            anyhow::bail!(
+                "The identity provider metadata is for another issuer ({}).",
+                metadata.issuer
+            );
+        }
+        Ok(metadata)
+    }
+}
+
+/// The user an identity provider account signs in as.
+///
+/// Accounts are matched on their subject identifier. The first time around, an
+/// account is linked to the user with the same email address, provided the
+/// provider has verified it. Emails are compared regardless of case, so an
+/// address shared by several users, e.g. `Alice@corp.com` and
+/// `alice@corp.com`, links none of them.
+#[tracing::instrument(name = "Find the user for an OIDC identity", skip(claims, pool))]
+pub async fn find_oidc_user(
+    claims: &IdTokenClaims,
+    pool: &PgPool,
+) -> Result<Option<(Uuid, String)>, anyhow::Error> {
+    let linked = sqlx::query!(
+        "SELECT user_id, username FROM users WHERE oidc_subject = $1 AND disabled_at IS NULL",
+        claims.sub,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to look up the user linked to an OIDC subject.")?;
+    if let Some(user) = linked {
+        return Ok(Some((user.user_id, user.username)));
+    }
+
+    let Some(email) = claims
+        .email
+        .as_deref()
+        .filter(|_| claims.email_verified == Some(true))
+    else {
+        return Ok(None);
+    };
+    let candidates = sqlx::query_scalar!(
+        "SELECT user_id FROM users WHERE lower(email) = lower($1) LIMIT 2",
+        email,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to look up the users with an email address.")?;
+    let [user_id] = candidates[..] else {
+        if !candidates.is_empty() {
+            tracing::warn!("Several users share the email address: none of them is linked.");
+        }
+        return Ok(None);
+    };
+    let linked = sqlx::query!(
+        r#"
+        UPDATE users
+        SET oidc_subject = $1
+        WHERE user_id = $2 AND oidc_subject IS NULL AND disabled_at IS NULL
+        RETURNING user_id, username
+        "#,
+        claims.sub,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to link an OIDC subject to a user.")?;
+    Ok(linked.map(|user| (user.user_id, user.username)))
+}
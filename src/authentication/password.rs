@@ -1,5 +1,6 @@
 //! src/authentication/password.rs
 
+use crate::metrics::metrics;
 use crate::telemetry::spawn_blocking_with_tracing;
 use anyhow::Context;
 use argon2::{
@@ -8,6 +9,7 @@ use argon2::{
 };
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
+use tokio_util::task::TaskTracker;
 
 #[derive(thiserror::Error, Debug)]
 pub enum AuthError {
@@ -22,18 +24,69 @@ pub struct Credentials {
     pub password: Secret<String>,
 }
 
-#[tracing::instrument(name = "Validate credentials", skip(credentials, pool))]
+/// The Argon2 parameters new password hashes are computed with.
+///
+/// It also carries a dummy hash computed with the same parameters, so that
+/// rejecting an unknown username takes as long as rejecting a wrong password.
+#[derive(Clone)]
+pub struct PasswordHashing {
+    params: Params,
+    dummy_hash: String,
+    /// Where hash upgrades run, so that shutdown can wait for them.
+    upgrades: TaskTracker,
+}
+
+impl PasswordHashing {
+    pub fn new(params: Params) -> Result<Self, anyhow::Error> {
+        let dummy_password = Secret::new(uuid::Uuid::new_v4().to_string());
+        let dummy_hash = compute_password_hash(dummy_password, &params)?;
+        Ok(Self {
+            params,
+            dummy_hash: dummy_hash.expose_secret().to_owned(),
+            upgrades: TaskTracker::new(),
+        })
+    }
+
+    /// Run hash upgrades on `tasks` rather than on a tracker of their own.
+    pub fn with_task_tracker(self, tasks: TaskTracker) -> Self {
+        Self {
+            upgrades: tasks,
+            ..self
+        }
+    }
+
+    /// Hash `password` with the current parameters.
+    ///
+    /// This is CPU-bound: call it from a blocking task.
+    pub fn compute_hash(&self, password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
+        compute_password_hash(password, &self.params)
+    }
+
+    /// Whether a stored hash was computed with another algorithm, version or
+    /// set of parameters than the ones we currently use.
+    pub fn is_outdated(&self, password_hash: &Secret<String>) -> bool {
+        let Ok(password_hash) = PasswordHash::new(password_hash.expose_secret()) else {
+            return false;
+        };
+        let Ok(params) = Params::try_from(&password_hash) else {
+            return true;
+        };
+        password_hash.algorithm != Algorithm::Argon2id.ident()
+            || password_hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+    }
+}
+
+#[tracing::instrument(name = "Validate credentials", skip(credentials, pool, hashing))]
 pub async fn validate_credentials(
     credentials: Credentials,
     pool: &PgPool,
+    hashing: &PasswordHashing,
 ) -> Result<uuid::Uuid, AuthError> {
     let mut user_id = None;
-    let mut expected_password_hash = Secret::new(
-        "$argon2id$v=19$m=15000,t=2,p=1$\
-        gZiV/M1gPc22ElAH/Jh1Hw$\
-        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno"
-            .to_string(),
-    );
+    let mut expected_password_hash = Secret::new(hashing.dummy_hash.clone());
 
     if let Some((stored_user_id, stored_password_hash)) =
         get_stored_credentials(&credentials.username, pool).await?
@@ -41,15 +94,36 @@ pub async fn validate_credentials(
         user_id = Some(stored_user_id);
         expected_password_hash = stored_password_hash;
     }
-    spawn_blocking_with_tracing(move || {
-        verify_password_hash(expected_password_hash, credentials.password)
+    let password = credentials.password;
+    let (expected_password_hash, password) = spawn_blocking_with_tracing(move || {
+        verify_password_hash(&expected_password_hash, &password)
+            .map(|_| (expected_password_hash, password))
     })
     .await
     .context("Failed to spawn blocking task.")??;
 
-    user_id
+    let user_id = user_id
         .ok_or_else(|| anyhow::anyhow!("Unknown username."))
-        .map_err(AuthError::InvalidCredentials)
+        .map_err(AuthError::InvalidCredentials)?;
+
+    if hashing.is_outdated(&expected_password_hash) {
+        let upgrade = upgrade_password_hash(
+            user_id,
+            expected_password_hash,
+            password,
+            hashing.clone(),
+            pool.clone(),
+        );
+        hashing.upgrades.spawn(async move {
+            if let Err(e) = upgrade.await {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    "Failed to upgrade an outdated password hash."
+                );
+            }
+        });
+    }
+    Ok(user_id)
 }
 
 #[tracing::instrument(name = "Get stored credentials", skip(username, pool))]
@@ -61,7 +135,7 @@ pub async fn get_stored_credentials(
         r#"
         SELECT user_id, password_hash
         FROM users
-        WHERE username = $1
+        WHERE username = $1 AND disabled_at IS NULL
         "#,
         username,
     )
@@ -77,29 +151,67 @@ pub async fn get_stored_credentials(
     skip(expected_password_hash, password_candidate)
 )]
 pub fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
+    expected_password_hash: &Secret<String>,
+    password_candidate: &Secret<String>,
 ) -> Result<(), AuthError> {
     let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
         .context("Failed to parse hash in PHC string format.")
         .map_err(AuthError::UnexpectedError)?;
 
-    Argon2::default()
-        .verify_password(
-            password_candidate.expose_secret().as_bytes(),
-            &expected_password_hash,
-        )
+    let started_at = std::time::Instant::now();
+    let outcome = Argon2::default().verify_password(
+        password_candidate.expose_secret().as_bytes(),
+        &expected_password_hash,
+    );
+    metrics().observe_password_verification(started_at.elapsed());
+    outcome
         .context("Invalid password.")
         .map_err(AuthError::InvalidCredentials)
 }
 
-#[tracing::instrument(name = "Change password", skip(password, pool))]
+/// Rehash a password whose stored hash is outdated.
+///
+/// The update only applies if the stored hash is still the one we verified
+/// against, so a concurrent password change is never overwritten.
+#[tracing::instrument(
+    name = "Upgrade password hash",
+    skip(outdated_password_hash, password, hashing, pool)
+)]
+async fn upgrade_password_hash(
+    user_id: uuid::Uuid,
+    outdated_password_hash: Secret<String>,
+    password: Secret<String>,
+    hashing: PasswordHashing,
+    pool: PgPool,
+) -> Result<(), anyhow::Error> {
+    let password_hash = spawn_blocking_with_tracing(move || hashing.compute_hash(password))
+        .await?
+        .context("Failed to hash password")?;
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password_hash = $1
+        WHERE user_id = $2 AND password_hash = $3
+        "#,
+        password_hash.expose_secret(),
+        user_id,
+        outdated_password_hash.expose_secret(),
+    )
+    .execute(&pool)
+    .await
+    .context("Failed to store the upgraded password hash in the database.")?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Change password", skip(password, pool, hashing))]
 pub async fn change_password(
     user_id: uuid::Uuid,
     password: Secret<String>,
     pool: &PgPool,
+    hashing: &PasswordHashing,
 ) -> Result<(), anyhow::Error> {
-    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+    let hashing = hashing.clone();
+    let password_hash = spawn_blocking_with_tracing(move || hashing.compute_hash(password))
         .await?
         .context("Failed to hash password")?;
     sqlx::query!(
@@ -117,14 +229,13 @@ pub async fn change_password(
     Ok(())
 }
 
-fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
+fn compute_password_hash(
+    password: Secret<String>,
+    params: &Params,
+) -> Result<Secret<String>, anyhow::Error> {
     let salt = SaltString::generate(&mut rand::thread_rng());
-    let password_hash = Argon2::new(
-        Algorithm::Argon2id,
-        Version::V0x13,
-        Params::new(15000, 2, 1, None).unwrap(),
-    )
-    .hash_password(password.expose_secret().as_bytes(), &salt)?
-    .to_string();
+    let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone())
+        .hash_password(password.expose_secret().as_bytes(), &salt)?
+        .to_string();
     Ok(Secret::new(password_hash))
 }
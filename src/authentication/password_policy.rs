@@ -0,0 +1,495 @@
+//! src/authentication/password_policy.rs
+//!
+//! The rules every new password must pass, wherever it is set.
+//!
+//! Strength is estimated the way zxcvbn does it: the password is split into the
+//! patterns an attacker would try first (common passwords, repeats, sequences,
+//! years), the number of guesses needed to find each piece is multiplied out,
+//! and the total is mapped to a score from 0 to 4.
+
+use chrono::Datelike;
+use secrecy::{ExposeSecret, Secret};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+const MIN_LENGTH: usize = 12;
+const MAX_LENGTH: usize = 129;
+/// The lowest acceptable score: "safely unguessable" in zxcvbn's words.
+const MIN_SCORE: u8 = 3;
+/// The guesses needed for each character not covered by a known pattern.
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+
+/// Common and breached passwords, by rank.
+static COMMON_PASSWORDS: LazyLock<HashMap<&'static str, usize>> = LazyLock::new(|| {
+    include_str!("common_passwords.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .zip(1..)
+        .collect()
+});
+
+/// No longer token of a password can be a common password.
+static LONGEST_COMMON_PASSWORD: LazyLock<usize> = LazyLock::new(|| {
+    COMMON_PASSWORDS
+        .keys()
+        .map(|password| password.chars().count())
+        .max()
+        .unwrap_or(0)
+});
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum PasswordRejection {
+    #[error("Invalid password length - the new password must be between 12 and 129 characters.")]
+    InvalidLength,
+    #[error("The new password must not contain your username.")]
+    ContainsUsername,
+    #[error("This password appears in a list of breached passwords - please choose another one.")]
+    Breached,
+    #[error("This password is too easy to guess. {0}")]
+    TooWeak(&'static str),
+}
+
+/// Check `password`, about to be set for `username`, against the policy.
+pub fn check_password_policy(
+    password: &Secret<String>,
+    username: &str,
+) -> Result<(), PasswordRejection> {
+    let password = password.expose_secret();
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&password.chars().count()) {
+        return Err(PasswordRejection::InvalidLength);
+    }
+    let lowercase = password.to_lowercase();
+    let username = username.trim().to_lowercase();
+    // Shorter usernames would turn up by chance.
+    if username.chars().count() >= 3 && lowercase.contains(&username) {
+        return Err(PasswordRejection::ContainsUsername);
+    }
+    if COMMON_PASSWORDS.contains_key(lowercase.as_str()) {
+        return Err(PasswordRejection::Breached);
+    }
+    let estimate = estimate_strength(password);
+    if estimate.score < MIN_SCORE {
+        return Err(PasswordRejection::TooWeak(estimate.feedback));
+    }
+    Ok(())
+}
+
+struct StrengthEstimate {
+    /// From 0, too guessable, to 4, very unguessable.
+    score: u8,
+    /// What makes the password guessable.
+    feedback: &'static str,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Pattern {
+    CommonPassword { l33t: bool },
+    Repeat,
+    Sequence,
+    Year,
+}
+
+impl Pattern {
+    fn feedback(&self) -> &'static str {
+        match self {
+            Pattern::CommonPassword { l33t: true } => {
+                "Predictable substitutions like '@' instead of 'a' don't help very much."
+            }
+            Pattern::CommonPassword { l33t: false } => {
+                "It is built around a common password - add a few uncommon words."
+            }
+            Pattern::Repeat => r#"Repeats like "aaa" or "abcabc" are easy to guess."#,
+            Pattern::Sequence => r#"Sequences like "abc" or "6543" are easy to guess."#,
+            Pattern::Year => "Recent years are easy to guess.",
+        }
+    }
+}
+
+struct Match {
+    start: usize,
+    end: usize,
+    guesses: f64,
+    pattern: Pattern,
+}
+
+fn estimate_strength(password: &str) -> StrengthEstimate {
+    let chars: Vec<char> = password.chars().collect();
+    let (guesses, patterns) = most_guessable_split(&chars);
+    let score = match guesses {
+        g if g < 1e3 + 5.0 => 0,
+        g if g < 1e6 + 5.0 => 1,
+        g if g < 1e8 + 5.0 => 2,
+        g if g < 1e10 + 5.0 => 3,
+        _ => 4,
+    };
+    // The longest pattern is the one most worth pointing out.
+    let feedback = patterns
+        .iter()
+        .max_by_key(|(length, _)| *length)
+        .map(|(_, pattern)| pattern.feedback())
+        .unwrap_or("Add a few more words, uncommon ones are better.");
+    StrengthEstimate { score, feedback }
+}
+
+/// The fewest guesses needed to find `chars`, and the patterns (with their
+/// length) the attacker would go through to get there.
+fn most_guessable_split(chars: &[char]) -> (f64, Vec<(usize, Pattern)>) {
+    most_guessable_split_with(chars, &mut HashMap::new())
+}
+
+/// `unit_guesses` remembers the guesses for the units of repeats, which the
+/// same password has many of: splitting each again would take exponential time.
+fn most_guessable_split_with(
+    chars: &[char],
+    unit_guesses: &mut HashMap<Vec<char>, f64>,
+) -> (f64, Vec<(usize, Pattern)>) {
+    let mut matches = find_matches(chars, unit_guesses);
+    matches.sort_by_key(|m| m.end);
+    // `best[i]` is the fewest guesses for the first `i` characters, reached
+    // through `last[i]`, the match ending there (`None` for a single
+    // bruteforced character).
+    let mut best = vec![1.0; chars.len() + 1];
+    let mut last: Vec<Option<usize>> = vec![None; chars.len() + 1];
+    let mut next_match = 0;
+    for end in 1..=chars.len() {
+        best[end] = best[end - 1] * BRUTEFORCE_CARDINALITY;
+        while let Some(m) = matches.get(next_match).filter(|m| m.end == end) {
+            let guesses = best[m.start] * m.guesses;
+            if guesses < best[end] {
+                best[end] = guesses;
+                last[end] = Some(next_match);
+            }
+            next_match += 1;
+        }
+    }
+
+    let mut patterns = vec![];
+    let mut end = chars.len();
+    while end > 0 {
+        match last[end] {
+            Some(index) => {
+                let m = &matches[index];
+                patterns.push((m.end - m.start, m.pattern));
+                end = m.start;
+            }
+            None => end -= 1,
+        }
+    }
+    (best[chars.len()], patterns)
+}
+
+fn find_matches(chars: &[char], unit_guesses: &mut HashMap<Vec<char>, f64>) -> Vec<Match> {
+    let mut matches = common_password_matches(chars);
+    matches.extend(repeat_matches(chars, unit_guesses));
+    matches.extend(sequence_matches(chars));
+    matches.extend(year_matches(chars));
+    matches
+}
+
+fn common_password_matches(chars: &[char]) -> Vec<Match> {
+    let lowercase: Vec<char> = chars.iter().flat_map(|c| c.to_lowercase()).collect();
+    if lowercase.len() != chars.len() {
+        // Case folding changed the length: offsets would not line up.
+        return vec![];
+    }
+    // Substitutions are undone character by character: once is enough for
+    // every token.
+    let unleeted: Vec<Vec<char>> = ['i', 'l']
+        .into_iter()
+        .map(|one| lowercase.iter().map(|c| unleet(*c, one)).collect())
+        .collect();
+    let mut word = String::new();
+    let mut reversed = String::new();
+    let mut matches = vec![];
+    for start in 0..chars.len() {
+        for end in start + 3..=chars.len().min(start + *LONGEST_COMMON_PASSWORD) {
+            let token = &lowercase[start..end];
+            let mut candidates = vec![(token, false)];
+            for unleeted in &unleeted {
+                let candidate = &unleeted[start..end];
+                if candidates.iter().all(|(word, _)| *word != candidate) {
+                    candidates.push((candidate, true));
+                }
+            }
+            for (candidate, l33t) in candidates {
+                word.clear();
+                word.extend(candidate);
+                reversed.clear();
+                reversed.extend(candidate.iter().rev());
+                let rank = match (
+                    COMMON_PASSWORDS.get(word.as_str()),
+                    COMMON_PASSWORDS.get(reversed.as_str()),
+                ) {
+                    (Some(rank), _) => *rank as f64,
+                    (None, Some(rank)) => *rank as f64 * 2.0,
+                    (None, None) => continue,
+                };
+                let variations = uppercase_variations(&chars[start..end]);
+                let l33t_variations = if l33t { 2.0 } else { 1.0 };
+                matches.push(Match {
+                    start,
+                    end,
+                    guesses: rank * variations * l33t_variations,
+                    pattern: Pattern::CommonPassword { l33t },
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// How many capitalisations an attacker would try for a word.
+fn uppercase_variations(chars: &[char]) -> f64 {
+    let upper = chars.iter().filter(|c| c.is_uppercase()).count();
+    let lower = chars.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        1.0
+    } else if lower == 0
+        || (upper == 1 && (chars[0].is_uppercase() || chars[chars.len() - 1].is_uppercase()))
+    {
+        2.0
+    } else {
+        2f64.powi(upper.min(lower) as i32 + 1)
+    }
+}
+
+/// `c` with a common l33t substitution undone, reading `1`, `!` and `|` as `one`.
+fn unleet(c: char, one: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '8' => 'b',
+        '(' => 'c',
+        '3' => 'e',
+        '6' | '9' => 'g',
+        '1' | '!' | '|' => one,
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' | '+' => 't',
+        '2' => 'z',
+        c => c,
+    }
+}
+
+fn repeat_matches(chars: &[char], unit_guesses: &mut HashMap<Vec<char>, f64>) -> Vec<Match> {
+    let mut matches = vec![];
+    for start in 0..chars.len() {
+        for unit_length in 1..=(chars.len() - start) / 2 {
+            let unit = &chars[start..start + unit_length];
+            if is_repeat(unit) {
+                // The repeat of its own unit is at least as guessable.
+                continue;
+            }
+            let mut count = 1;
+            while chars[start + count * unit_length..]
+                .get(..unit_length)
+                .is_some_and(|next| next == unit)
+            {
+                count += 1;
+            }
+            let length = count * unit_length;
+            if count >= 2 && length >= 3 {
+                let guesses = match unit_guesses.get(unit) {
+                    Some(guesses) => *guesses,
+                    None => {
+                        let (guesses, _) = most_guessable_split_with(unit, unit_guesses);
+                        unit_guesses.insert(unit.to_vec(), guesses);
+                        guesses
+                    }
+                };
+                matches.push(Match {
+                    start,
+                    end: start + length,
+                    guesses: guesses * count as f64,
+                    pattern: Pattern::Repeat,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Whether `chars` is a shorter unit repeated, e.g. `abab`.
+fn is_repeat(chars: &[char]) -> bool {
+    (1..=chars.len() / 2).any(|unit_length| {
+        chars.len().is_multiple_of(unit_length)
+            && chars
+                .chunks(unit_length)
+                .all(|chunk| chunk == &chars[..unit_length])
+    })
+}
+
+fn sequence_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = vec![];
+    let same_class = |a: char, b: char| {
+        (a.is_ascii_digit() && b.is_ascii_digit())
+            || (a.is_ascii_lowercase() && b.is_ascii_lowercase())
+            || (a.is_ascii_uppercase() && b.is_ascii_uppercase())
+    };
+    let mut start = 0;
+    while start + 2 < chars.len() {
+        let delta = chars[start + 1] as i32 - chars[start] as i32;
+        let mut end = start + 1;
+        while end < chars.len()
+            && (delta == 1 || delta == -1)
+            && same_class(chars[start], chars[end])
+            && chars[end] as i32 - chars[end - 1] as i32 == delta
+        {
+            end += 1;
+        }
+        if end - start >= 3 {
+            let first = chars[start];
+            let base = if "aAzZ019".contains(first) {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let direction = if delta == 1 { 1.0 } else { 2.0 };
+            matches.push(Match {
+                start,
+                end,
+                guesses: base * (end - start) as f64 * direction,
+                pattern: Pattern::Sequence,
+            });
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+    matches
+}
+
+fn year_matches(chars: &[char]) -> Vec<Match> {
+    let current_year = chrono::Utc::now().year();
+    (0..chars.len().saturating_sub(3))
+        .filter_map(|start| {
+            let year: i32 = chars[start..start + 4]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .ok()?;
+            (1900..=2099).contains(&year).then(|| Match {
+                start,
+                end: start + 4,
+                guesses: ((year - current_year).abs() as f64).max(20.0),
+                pattern: Pattern::Year,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(password: &str, username: &str) -> Result<(), PasswordRejection> {
+        check_password_policy(&Secret::new(password.to_string()), username)
+    }
+
+    #[test]
+    fn long_random_passwords_are_accepted() {
+        assert_eq!(check("q8Zt-f3Lw!m0Xr", "admin"), Ok(()));
+    }
+
+    #[test]
+    fn passphrases_of_uncommon_words_are_accepted() {
+        assert_eq!(check("correct-horse-battery-staple", "admin"), Ok(()));
+    }
+
+    #[test]
+    fn passwords_must_be_between_12_and_129_characters() {
+        assert_eq!(
+            check("q8Zt-f3Lw!m", "admin"),
+            Err(PasswordRejection::InvalidLength)
+        );
+        let long = "q8Zt-f3Lw!m0Xr".repeat(10);
+        assert_eq!(check(&long, "admin"), Err(PasswordRejection::InvalidLength));
+    }
+
+    #[test]
+    fn passwords_must_not_contain_the_username() {
+        assert_eq!(
+            check("xX-Margaret-Quokka-9", "margaret"),
+            Err(PasswordRejection::ContainsUsername)
+        );
+    }
+
+    #[test]
+    fn listed_passwords_are_rejected_as_breached() {
+        assert_eq!(
+            check("Password1234", "admin"),
+            Err(PasswordRejection::Breached)
+        );
+        assert_eq!(
+            check("qwertyuiop123", "admin"),
+            Err(PasswordRejection::Breached)
+        );
+    }
+
+    #[test]
+    fn common_passwords_with_l33t_substitutions_are_too_weak() {
+        assert_eq!(
+            check("P@ssw0rd1234", "admin"),
+            Err(PasswordRejection::TooWeak(
+                Pattern::CommonPassword { l33t: true }.feedback()
+            ))
+        );
+    }
+
+    #[test]
+    fn common_passwords_glued_together_are_too_weak() {
+        assert_eq!(
+            check("monkeydragonbatman", "admin"),
+            Err(PasswordRejection::TooWeak(
+                Pattern::CommonPassword { l33t: false }.feedback()
+            ))
+        );
+    }
+
+    #[test]
+    fn repeats_are_too_weak() {
+        assert_eq!(
+            check("zzzzzzzzzzzzzzzz", "admin"),
+            Err(PasswordRejection::TooWeak(Pattern::Repeat.feedback()))
+        );
+    }
+
+    #[test]
+    fn the_longest_repeats_are_too_weak() {
+        for unit in ["a", "ab", "aab", "abcabd"] {
+            let password: String = unit.chars().cycle().take(MAX_LENGTH).collect();
+            assert_eq!(
+                check(&password, "admin"),
+                Err(PasswordRejection::TooWeak(Pattern::Repeat.feedback()))
+            );
+        }
+    }
+
+    #[test]
+    fn each_repeated_unit_is_split_once() {
+        for unit in ["a", "ab", "aab", "abcabd"] {
+            let password: Vec<char> = unit.chars().cycle().take(MAX_LENGTH).collect();
+            let mut unit_guesses = HashMap::new();
+            most_guessable_split_with(&password, &mut unit_guesses);
+            // Besides the password, only the rotations of its unit are split:
+            // the longer units found along the way are repeats of them.
+            assert_eq!(unit_guesses.len(), unit.len());
+        }
+    }
+
+    #[test]
+    fn sequences_are_too_weak() {
+        assert_eq!(
+            check("abcdefghijklmnop", "admin"),
+            Err(PasswordRejection::TooWeak(Pattern::Sequence.feedback()))
+        );
+    }
+
+    #[test]
+    fn guesses_grow_with_every_uncovered_character() {
+        let (short, _) = most_guessable_split(&['x', '7', '#', 'q']);
+        let (long, _) = most_guessable_split(&['x', '7', '#', 'q', 'k', '2']);
+        assert_eq!(long, short * 100.0);
+    }
+}
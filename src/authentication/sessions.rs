@@ -0,0 +1,198 @@
+//! src/authentication/sessions.rs
+//!
+//! The index of each user's sessions, kept in Postgres whichever store holds
+//! the session state. Revocation works the same with every store. It also
+//! survives Redis being flushed, and is checked by the authentication
+//! middleware on every request: a revoked session has no row, so its cookie
+//! stops working even if the store still holds its state.
+
+use actix_web::http::header::USER_AGENT;
+use actix_web::HttpRequest;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::types::PgInterval;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::configuration::SessionLifetime;
+
+/// Where and how a session was opened.
+#[derive(Clone)]
+pub struct SessionMetadata {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl SessionMetadata {
+    pub fn from_request(request: &HttpRequest) -> Self {
+        let user_agent = request
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|h| h.to_str().ok())
+            .map(ToOwned::to_owned);
+        let ip_address = request
+            .connection_info()
+            .realip_remote_addr()
+            .map(ToOwned::to_owned);
+        Self {
+            user_agent,
+            ip_address,
+        }
+    }
+}
+
+pub struct ActiveSession {
+    pub session_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Add a freshly opened session to its user's index.
+///
+/// Entries whose session has timed out can no longer match a live session, so
+/// they are pruned here.
+#[tracing::instrument(name = "Record a new session", skip(metadata, pool))]
+pub async fn record_session(
+    session_id: Uuid,
+    user_id: Uuid,
+    metadata: SessionMetadata,
+    lifetime: SessionLifetime,
+    pool: &PgPool,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE user_id = $1 AND (expires_at < now() OR last_seen_at + idle_timeout < now())
+        "#,
+        user_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to prune expired sessions.")?;
+    let absolute_timeout = PgInterval::try_from(lifetime.absolute_timeout)
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("The absolute session timeout is out of range.")?;
+    let idle_timeout = PgInterval::try_from(lifetime.idle_timeout)
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("The idle session timeout is out of range.")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO user_sessions (
+            session_id, user_id, created_at, last_seen_at, expires_at, idle_timeout,
+            user_agent, ip_address
+        )
+        VALUES ($1, $2, now(), now(), now() + $3::interval, $4, $5, $6)
+        "#,
+        session_id,
+        user_id,
+        absolute_timeout,
+        idle_timeout,
+        metadata.user_agent,
+        metadata.ip_address,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record a new session.")?;
+    Ok(())
+}
+
+/// Mark a session as seen, returning `false` if it has been revoked or has
+/// outlived its absolute timeout.
+#[tracing::instrument(name = "Touch session", skip(pool))]
+pub async fn touch_session(
+    session_id: Uuid,
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE user_sessions
+        SET last_seen_at = now()
+        WHERE session_id = $1 AND user_id = $2 AND expires_at > now()
+        "#,
+        session_id,
+        user_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the session's last seen time.")?;
+    Ok(result.rows_affected() == 1)
+}
+
+#[tracing::instrument(name = "Get active sessions", skip(pool))]
+pub async fn get_active_sessions(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<ActiveSession>, anyhow::Error> {
+    let sessions = sqlx::query_as!(
+        ActiveSession,
+        r#"
+        SELECT session_id, created_at, last_seen_at, user_agent, ip_address
+        FROM user_sessions
+        WHERE user_id = $1 AND expires_at > now() AND last_seen_at + idle_timeout > now()
+        ORDER BY last_seen_at DESC
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the active sessions.")?;
+    Ok(sessions)
+}
+
+/// Revoke one of `user_id`'s sessions, returning `false` if there was no such session.
+#[tracing::instrument(name = "Revoke session", skip(pool))]
+pub async fn revoke_session(
+    user_id: Uuid,
+    session_id: Uuid,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE session_id = $1 AND user_id = $2
+        "#,
+        session_id,
+        user_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke a session.")?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Revoke every session of `user_id` except `current_session_id`.
+#[tracing::instrument(name = "Revoke other sessions", skip(pool))]
+pub async fn revoke_other_sessions(
+    user_id: Uuid,
+    current_session_id: Uuid,
+    pool: &PgPool,
+) -> Result<u64, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE user_id = $1 AND session_id <> $2
+        "#,
+        user_id,
+        current_session_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the other sessions.")?;
+    Ok(result.rows_affected())
+}
+
+/// Revoke every session of `user_id`, logging them out everywhere.
+#[tracing::instrument(name = "Revoke all sessions", skip(executor))]
+pub async fn revoke_all_sessions(
+    user_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<u64, anyhow::Error> {
+    let result = sqlx::query!("DELETE FROM user_sessions WHERE user_id = $1", user_id)
+        .execute(executor)
+        .await
+        .context("Failed to revoke all sessions.")?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,147 @@
+//! src/authentication/users.rs
+
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{revoke_all_sessions, PasswordHashing};
+use crate::telemetry::spawn_blocking_with_tracing;
+
+/// Owners can do everything admins can, and administer the deployment itself.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UserRole {
+    Owner,
+    Admin,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Owner => "owner",
+            UserRole::Admin => "admin",
+        }
+    }
+}
+
+impl TryFrom<&str> for UserRole {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "owner" => Ok(Self::Owner),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!("{} is not a known user role.", other)),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct UserSummary {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: String,
+    pub email: Option<String>,
+    pub sso_linked: bool,
+    pub disabled: bool,
+}
+
+#[tracing::instrument(name = "Create user", skip(password, pool, hashing))]
+pub async fn create_user(
+    username: &str,
+    email: Option<&str>,
+    role: UserRole,
+    password: Secret<String>,
+    pool: &PgPool,
+    hashing: &PasswordHashing,
+) -> Result<Uuid, anyhow::Error> {
+    let hashing = hashing.clone();
+    let password_hash = spawn_blocking_with_tracing(move || hashing.compute_hash(password))
+        .await?
+        .context("Failed to hash password")?;
+    let user_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, email, role, password_hash)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        username,
+        email,
+        role.as_str(),
+        password_hash.expose_secret(),
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store a new user in the database.")?;
+    Ok(user_id)
+}
+
+#[tracing::instrument(name = "Get user ID", skip(pool))]
+pub async fn get_user_id(username: &str, pool: &PgPool) -> Result<Option<Uuid>, anyhow::Error> {
+    let user_id = sqlx::query_scalar!("SELECT user_id FROM users WHERE username = $1", username)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to perform a query to retrieve a user ID.")?;
+    Ok(user_id)
+}
+
+#[tracing::instrument(name = "Get user role", skip(pool))]
+pub async fn get_user_role(user_id: Uuid, pool: &PgPool) -> Result<UserRole, anyhow::Error> {
+    let role = sqlx::query_scalar!("SELECT role FROM users WHERE user_id = $1", user_id)
+        .fetch_one(pool)
+        .await
+        .context("Failed to perform a query to retrieve a user's role.")?;
+    UserRole::try_from(role.as_str()).map_err(anyhow::Error::msg)
+}
+
+#[tracing::instrument(name = "List users", skip(pool))]
+pub async fn list_users(pool: &PgPool) -> Result<Vec<UserSummary>, anyhow::Error> {
+    let users = sqlx::query_as!(
+        UserSummary,
+        r#"
+        SELECT
+            user_id,
+            username,
+            role,
+            email,
+            oidc_subject IS NOT NULL AS "sso_linked!",
+            disabled_at IS NOT NULL AS "disabled!"
+        FROM users
+        ORDER BY username
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve users.")?;
+    Ok(users)
+}
+
+/// Stop a user from logging in, by any means, and end their open sessions.
+///
+/// Returns `false` if the user was already disabled.
+#[tracing::instrument(name = "Disable user", skip(pool))]
+pub async fn disable_user(user_id: Uuid, pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let disabled = sqlx::query!(
+        r#"
+        UPDATE users
+        SET disabled_at = now()
+        WHERE user_id = $1 AND disabled_at IS NULL
+        "#,
+        user_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to disable a user.")?
+    .rows_affected();
+    revoke_all_sessions(user_id, &mut *transaction).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to disable a user.")?;
+    Ok(disabled > 0)
+}
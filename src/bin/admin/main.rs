@@ -0,0 +1,118 @@
+//! src/bin/admin/main.rs
+//!
+//! `zero2prod-admin`: day-to-day administration of a deployment, against the
+//! database and email provider configured for the current environment.
+
+mod output;
+mod subscribers;
+mod users;
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use output::{OutputFormat, Report};
+use std::path::PathBuf;
+use zero2prod::configuration::{load_configuration, Settings};
+use zero2prod::domain::SubscriberEmail;
+use zero2prod::email_client::EmailClient;
+use zero2prod::startup::get_connection_pool;
+use zero2prod::telemetry::{get_subscriber, init_subscriber};
+
+#[derive(Parser)]
+#[command(name = "zero2prod-admin", about = "Administer a zero2prod deployment")]
+struct Cli {
+    /// How results are printed.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human, global = true)]
+    output: OutputFormat,
+    /// A YAML file applied over the configuration of `APP_ENVIRONMENT`.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage the users of the admin area.
+    #[command(subcommand)]
+    User(users::UserCommand),
+    /// Manage newsletter subscribers.
+    #[command(subcommand)]
+    Subscriber(subscribers::SubscriberCommand),
+    /// Apply pending database migrations.
+    Migrate,
+    /// Send an email through the configured provider.
+    SendTestEmail {
+        /// Where to send it.
+        recipient: String,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let cli = Cli::parse();
+    // Logs go to stderr, to keep stdout for the command's output.
+    let (subscriber, _) = get_subscriber(
+        "zero2prod-admin".into(),
+        "warn".into(),
+        std::io::stderr,
+        None,
+    );
+    init_subscriber(subscriber);
+
+    let configuration =
+        load_configuration(cli.config.as_deref()).context("Failed to read configuration.")?;
+    configuration.validate()?;
+    let report = match cli.command {
+        Command::User(command) => users::run(command, &configuration).await?,
+        Command::Subscriber(command) => subscribers::run(command, &configuration).await?,
+        Command::Migrate => migrate(&configuration).await?,
+        Command::SendTestEmail { recipient } => send_test_email(recipient, configuration).await?,
+    };
+    report.print(cli.output);
+    Ok(())
+}
+
+async fn migrate(configuration: &Settings) -> Result<Report, anyhow::Error> {
+    let pool = get_connection_pool(&configuration.database);
+    let migrator = sqlx::migrate!("./migrations");
+    migrator
+        .run(&pool)
+        .await
+        .context("Failed to migrate the database.")?;
+    let latest = migrator.iter().map(|m| m.version).max();
+    Ok(Report::new(
+        "The database schema is up to date.",
+        serde_json::json!({ "latest_migration": latest }),
+    ))
+}
+
+async fn send_test_email(
+    recipient: String,
+    configuration: Settings,
+) -> Result<Report, anyhow::Error> {
+    let recipient = SubscriberEmail::parse(recipient).map_err(anyhow::Error::msg)?;
+    let sender = configuration
+        .email_client
+        .sender()
+        .map_err(anyhow::Error::msg)?;
+    let timeout = configuration.email_client.timeout();
+    let email_client = EmailClient::new(
+        configuration.email_client.base_url,
+        sender,
+        configuration.email_client.authorization_token,
+        timeout,
+    );
+    email_client
+        .send_email(
+            &recipient,
+            "Test email",
+            "<p>This is a test email sent by <code>zero2prod-admin</code>.</p>",
+            "This is a test email sent by zero2prod-admin.",
+        )
+        .await
+        .context("Failed to send the test email.")?;
+    Ok(Report::new(
+        format!("A test email has been sent to {}.", recipient.as_ref()),
+        serde_json::json!({ "recipient": recipient.as_ref() }),
+    ))
+}
@@ -0,0 +1,31 @@
+//! src/bin/admin/output.rs
+
+use clap::ValueEnum;
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// The outcome of a command, ready to be printed in either format.
+pub struct Report {
+    human: String,
+    json: serde_json::Value,
+}
+
+impl Report {
+    pub fn new(human: impl Into<String>, json: serde_json::Value) -> Self {
+        Self {
+            human: human.into(),
+            json,
+        }
+    }
+
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Human => println!("{}", self.human),
+            OutputFormat::Json => println!("{}", self.json),
+        }
+    }
+}
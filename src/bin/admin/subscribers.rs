@@ -0,0 +1,193 @@
+//! src/bin/admin/subscribers.rs
+
+use anyhow::Context;
+use chrono::Utc;
+use clap::Subcommand;
+use sqlx::PgPool;
+use std::fmt::Write;
+use std::path::PathBuf;
+use uuid::Uuid;
+use zero2prod::audit::{record_audit_event, AuditAction, AuditEvent};
+use zero2prod::configuration::Settings;
+use zero2prod::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
+use zero2prod::startup::get_connection_pool;
+
+use crate::output::Report;
+
+#[derive(Subcommand)]
+pub enum SubscriberCommand {
+    /// Confirm a subscription without going through the confirmation email.
+    Confirm { email: String },
+    /// Delete a subscriber and their pending confirmation tokens.
+    Delete { email: String },
+    /// Import confirmed subscribers from a file of `email,name` lines.
+    ///
+    /// A leading `email,name` header is skipped, as are addresses that are
+    /// already subscribed.
+    Import { path: PathBuf },
+}
+
+pub async fn run(
+    command: SubscriberCommand,
+    configuration: &Settings,
+) -> Result<Report, anyhow::Error> {
+    let pool = get_connection_pool(&configuration.database);
+    match command {
+        SubscriberCommand::Confirm { email } => {
+            let subscriber_id = sqlx::query_scalar!(
+                r#"
+                UPDATE subscriptions
+                SET status = 'confirmed'
+                WHERE email = $1
+                RETURNING id
+                "#,
+                email,
+            )
+            .fetch_optional(&pool)
+            .await
+            .context("Failed to confirm a subscriber.")?
+            .with_context(|| format!("There is no subscriber with email {}.", email))?;
+            let event = AuditEvent::without_request(AuditAction::SubscriberConfirmed, None)
+                .with_details(serde_json::json!({
+                    "subscriber_id": subscriber_id,
+                    "channel": "cli",
+                }));
+            record_audit_event(&pool, event).await?;
+            Ok(Report::new(
+                format!("The subscription of {} is confirmed.", email),
+                serde_json::json!({ "subscriber_id": subscriber_id, "email": email }),
+            ))
+        }
+        SubscriberCommand::Delete { email } => {
+            let subscriber_id = delete_subscriber(&email, &pool)
+                .await?
+                .with_context(|| format!("There is no subscriber with email {}.", email))?;
+            let event = AuditEvent::without_request(AuditAction::SubscriberDeleted, None)
+                .with_details(serde_json::json!({
+                    "subscriber_id": subscriber_id,
+                    "email": email,
+                    "channel": "cli",
+                }));
+            record_audit_event(&pool, event).await?;
+            Ok(Report::new(
+                format!("Subscriber {} has been deleted.", email),
+                serde_json::json!({ "subscriber_id": subscriber_id, "email": email }),
+            ))
+        }
+        SubscriberCommand::Import { path } => {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}.", path.display()))?;
+            import_subscribers(&contents, &pool).await
+        }
+    }
+}
+
+async fn delete_subscriber(email: &str, pool: &PgPool) -> Result<Option<Uuid>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE subscriber_id IN (SELECT id FROM subscriptions WHERE email = $1)
+        "#,
+        email,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete a subscriber's tokens.")?;
+    let subscriber_id = sqlx::query_scalar!(
+        "DELETE FROM subscriptions WHERE email = $1 RETURNING id",
+        email
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to delete a subscriber.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to delete a subscriber.")?;
+    Ok(subscriber_id)
+}
+
+async fn import_subscribers(contents: &str, pool: &PgPool) -> Result<Report, anyhow::Error> {
+    let mut imported = 0;
+    let mut already_subscribed = 0;
+    let mut invalid = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (index == 0 && line.eq_ignore_ascii_case("email,name")) {
+            continue;
+        }
+        let subscriber = match parse_line(line) {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                invalid.push(serde_json::json!({ "line": index + 1, "error": e }));
+                continue;
+            }
+        };
+        let subscriber_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+            VALUES ($1, $2, $3, $4, 'confirmed')
+            ON CONFLICT (email) DO NOTHING
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            subscriber.email.as_ref(),
+            subscriber.name.as_ref(),
+            Utc::now(),
+        )
+        .fetch_optional(pool)
+        .await
+        .context("Failed to import a subscriber.")?;
+        match subscriber_id {
+            Some(subscriber_id) => {
+                let event = AuditEvent::without_request(AuditAction::SubscriberCreated, None)
+                    .with_details(serde_json::json!({
+                        "subscriber_id": subscriber_id,
+                        "email": subscriber.email.as_ref(),
+                        "channel": "cli",
+                    }));
+                record_audit_event(pool, event).await?;
+                imported += 1;
+            }
+            None => already_subscribed += 1,
+        }
+    }
+
+    let mut human = format!(
+        "Imported {} subscriber(s), {} already subscribed, {} invalid.",
+        imported,
+        already_subscribed,
+        invalid.len()
+    );
+    for entry in &invalid {
+        write!(
+            human,
+            "\nLine {}: {}",
+            entry["line"],
+            entry["error"].as_str().unwrap()
+        )
+        .unwrap();
+    }
+    Ok(Report::new(
+        human,
+        serde_json::json!({
+            "imported": imported,
+            "already_subscribed": already_subscribed,
+            "invalid": invalid,
+        }),
+    ))
+}
+
+fn parse_line(line: &str) -> Result<NewSubscriber, String> {
+    let (email, name) = line
+        .split_once(',')
+        .ok_or_else(|| "Expected `email,name`.".to_string())?;
+    Ok(NewSubscriber {
+        email: SubscriberEmail::parse(email.trim().to_owned())?,
+        name: SubscriberName::parse(name.trim().to_owned())?,
+    })
+}
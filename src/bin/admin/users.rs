@@ -0,0 +1,201 @@
+//! src/bin/admin/users.rs
+
+use anyhow::Context;
+use clap::Subcommand;
+use secrecy::Secret;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+use zero2prod::audit::{record_audit_event, AuditAction, AuditEvent};
+use zero2prod::authentication::{
+    change_password, check_password_policy, create_user, disable_user, get_user_id, list_users,
+    revoke_all_sessions, PasswordHashing, UserRole,
+};
+use zero2prod::configuration::Settings;
+use zero2prod::startup::get_connection_pool;
+use zero2prod::utils::random_token;
+
+use crate::output::Report;
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// Create a user. A password is generated unless one is read from stdin.
+    Create {
+        username: String,
+        /// The address single sign-on accounts are linked through.
+        #[arg(long)]
+        email: Option<String>,
+        /// Make the user an owner rather than an admin.
+        #[arg(long)]
+        owner: bool,
+        /// Read the password from the first line of stdin.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// List all users.
+    List,
+    /// Stop a user from logging in and end their sessions.
+    Disable { username: String },
+    /// Set a new password and end the user's sessions.
+    /// A password is generated unless one is read from stdin.
+    ResetPassword {
+        username: String,
+        /// Read the password from the first line of stdin.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+}
+
+pub async fn run(command: UserCommand, configuration: &Settings) -> Result<Report, anyhow::Error> {
+    let pool = get_connection_pool(&configuration.database);
+    match command {
+        UserCommand::Create {
+            username,
+            email,
+            owner,
+            password_stdin,
+        } => {
+            let hashing = password_hashing(configuration)?;
+            let (password, generated) = new_password(password_stdin, &username)?;
+            let role = if owner {
+                UserRole::Owner
+            } else {
+                UserRole::Admin
+            };
+            let user_id = create_user(&username, email.as_deref(), role, password, &pool, &hashing)
+                .await
+                .with_context(|| format!("Failed to create user {}.", username))?;
+            let event = AuditEvent::without_request(AuditAction::UserCreated, Some(user_id))
+                .with_details(serde_json::json!({
+                    "username": username,
+                    "role": role.as_str(),
+                    "channel": "cli",
+                }));
+            record_audit_event(&pool, event).await?;
+
+            let mut human = format!("Created {} {} ({}).", role.as_str(), username, user_id);
+            if let Some(password) = &generated {
+                write!(human, "\nGenerated password: {}", password).unwrap();
+            }
+            Ok(Report::new(
+                human,
+                serde_json::json!({
+                    "user_id": user_id,
+                    "username": username,
+                    "role": role.as_str(),
+                    "generated_password": generated,
+                }),
+            ))
+        }
+        UserCommand::List => {
+            let users = list_users(&pool).await?;
+            let mut human = String::new();
+            for user in &users {
+                let mut flags = vec![];
+                if user.sso_linked {
+                    flags.push("sso");
+                }
+                if user.disabled {
+                    flags.push("disabled");
+                }
+                writeln!(
+                    human,
+                    "{}\t{}\t{}\t{}\t{}",
+                    user.user_id,
+                    user.username,
+                    user.role,
+                    user.email.as_deref().unwrap_or("-"),
+                    flags.join(",")
+                )
+                .unwrap();
+            }
+            if users.is_empty() {
+                human.push_str("There are no users.");
+            }
+            Ok(Report::new(
+                human.trim_end(),
+                serde_json::json!({ "users": users }),
+            ))
+        }
+        UserCommand::Disable { username } => {
+            let user_id = find_user(&username, &pool).await?;
+            let disabled = disable_user(user_id, &pool).await?;
+            if disabled {
+                let event = AuditEvent::without_request(AuditAction::UserDisabled, Some(user_id))
+                    .with_details(serde_json::json!({ "username": username, "channel": "cli" }));
+                record_audit_event(&pool, event).await?;
+            }
+            let human = if disabled {
+                format!("User {} has been disabled.", username)
+            } else {
+                format!("User {} was already disabled.", username)
+            };
+            Ok(Report::new(
+                human,
+                serde_json::json!({ "user_id": user_id, "username": username, "disabled": true }),
+            ))
+        }
+        UserCommand::ResetPassword {
+            username,
+            password_stdin,
+        } => {
+            let user_id = find_user(&username, &pool).await?;
+            let hashing = password_hashing(configuration)?;
+            let (password, generated) = new_password(password_stdin, &username)?;
+            change_password(user_id, password, &pool, &hashing).await?;
+            let revoked_sessions = revoke_all_sessions(user_id, &pool).await?;
+            let event = AuditEvent::without_request(AuditAction::PasswordChanged, Some(user_id))
+                .with_details(serde_json::json!({ "channel": "cli" }));
+            record_audit_event(&pool, event).await?;
+
+            let mut human = format!(
+                "The password of {} has been reset and {} session(s) ended.",
+                username, revoked_sessions
+            );
+            if let Some(password) = &generated {
+                write!(human, "\nGenerated password: {}", password).unwrap();
+            }
+            Ok(Report::new(
+                human,
+                serde_json::json!({
+                    "user_id": user_id,
+                    "username": username,
+                    "revoked_sessions": revoked_sessions,
+                    "generated_password": generated,
+                }),
+            ))
+        }
+    }
+}
+
+fn password_hashing(configuration: &Settings) -> Result<PasswordHashing, anyhow::Error> {
+    configuration
+        .password_hashing
+        .params()
+        .context("Invalid password hashing parameters.")
+        .and_then(PasswordHashing::new)
+}
+
+async fn find_user(username: &str, pool: &PgPool) -> Result<Uuid, anyhow::Error> {
+    get_user_id(username, pool)
+        .await?
+        .with_context(|| format!("There is no user named {}.", username))
+}
+
+/// The password to set for `username`, and its plain value if we generated it.
+fn new_password(
+    from_stdin: bool,
+    username: &str,
+) -> Result<(Secret<String>, Option<String>), anyhow::Error> {
+    if !from_stdin {
+        let password = random_token(24);
+        return Ok((Secret::new(password.clone()), Some(password)));
+    }
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .context("Failed to read the password from stdin.")?;
+    let password = Secret::new(line.trim_end_matches(['\r', '\n']).to_owned());
+    check_password_policy(&password, username)?;
+    Ok((password, None))
+}
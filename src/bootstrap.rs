@@ -0,0 +1,123 @@
+//! src/bootstrap.rs
+
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{check_password_policy, PasswordHashing, UserRole};
+use crate::configuration::BootstrapOwnerSettings;
+use crate::telemetry::spawn_blocking_with_tracing;
+use crate::utils::random_token;
+
+/// Grants access to the one-time `/setup` page, which creates the first owner.
+#[derive(Clone)]
+pub struct SetupToken(Arc<Secret<String>>);
+
+impl SetupToken {
+    fn generate() -> Self {
+        Self(Arc::new(Secret::new(random_token(32))))
+    }
+}
+
+impl ExposeSecret<String> for SetupToken {
+    fn expose_secret(&self) -> &String {
+        self.0.expose_secret()
+    }
+}
+
+/// Make sure a fresh deployment can be administered.
+///
+/// Once there is an enabled user, there is nothing to do. Otherwise the owner
+/// is created from `owner` if it is configured; if it is not, a setup token is
+/// generated and logged, for whoever operates the deployment to pick up.
+#[tracing::instrument(name = "Bootstrap the first owner", skip_all)]
+pub async fn bootstrap(
+    owner: Option<BootstrapOwnerSettings>,
+    pool: &PgPool,
+    hashing: &PasswordHashing,
+) -> Result<Option<SetupToken>, anyhow::Error> {
+    if has_enabled_users(pool).await? {
+        return Ok(None);
+    }
+    if let Some(owner) = owner {
+        check_password_policy(&owner.password, &owner.username)
+            .context("The configured owner password does not meet the password policy.")?;
+        create_owner(
+            &owner.username,
+            owner.email.as_deref(),
+            owner.password,
+            pool,
+            hashing,
+        )
+        .await?;
+        tracing::info!(
+            username = %owner.username,
+            "Created the owner account from the configuration."
+        );
+        return Ok(None);
+    }
+    let setup_token = SetupToken::generate();
+    tracing::warn!(
+        "There are no users yet. Visit /setup?token={} to create the owner account.",
+        setup_token.expose_secret()
+    );
+    Ok(Some(setup_token))
+}
+
+#[tracing::instrument(name = "Check for enabled users", skip(pool))]
+pub async fn has_enabled_users(pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM users WHERE disabled_at IS NULL) AS "exists!""#
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to check whether there are enabled users.")?;
+    Ok(exists)
+}
+
+/// Create the first owner, returning `None` if someone beat us to it.
+#[tracing::instrument(name = "Create the first owner", skip(password, pool, hashing))]
+pub async fn create_owner(
+    username: &str,
+    email: Option<&str>,
+    password: Secret<String>,
+    pool: &PgPool,
+    hashing: &PasswordHashing,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let hashing = hashing.clone();
+    let password_hash = spawn_blocking_with_tracing(move || hashing.compute_hash(password))
+        .await?
+        .context("Failed to hash password")?;
+    let user_id = Uuid::new_v4();
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, email, role, password_hash)
+        SELECT $1, $2, $3, $4, $5
+        WHERE NOT EXISTS (SELECT 1 FROM users WHERE disabled_at IS NULL)
+        "#,
+        user_id,
+        username,
+        email,
+        UserRole::Owner.as_str(),
+        password_hash.expose_secret(),
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the owner in the database.")?
+    .rows_affected();
+    if created == 0 {
+        return Ok(None);
+    }
+    let event = AuditEvent::without_request(AuditAction::UserCreated, Some(user_id)).with_details(
+        serde_json::json!({
+            "username": username,
+            "role": UserRole::Owner.as_str(),
+            "channel": "bootstrap",
+        }),
+    );
+    record_audit_event(pool, event).await?;
+    Ok(Some(user_id))
+}
@@ -0,0 +1,344 @@
+//! src/bot_protection.rs
+//!
+//! Checks that keep scripts from using the public subscribe form to send
+//! confirmation emails to arbitrary addresses, without any outside service:
+//!
+//! - a honeypot field, hidden from people but filled in by naive bots;
+//! - a signed timestamp of when the form was rendered, as people take a few
+//!   seconds to fill it in and don't reuse it for hours;
+//! - optionally, a proof of work the browser computes before submitting,
+//!   which costs one visitor nothing and a mass sender a lot. It is bound to
+//!   the address being subscribed, so a solution can't be replayed for others.
+
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+use crate::configuration::BotProtectionSettings;
+use crate::reload::Reloadable;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum BotCheckError {
+    #[error("The form is invalid - please reload the page and try again.")]
+    InvalidFormToken,
+    #[error("The form was submitted too quickly - please try again.")]
+    SubmittedTooQuickly,
+    #[error("The form has expired - please reload the page and try again.")]
+    FormExpired,
+    #[error("The anti-spam challenge was not solved - please reload the page and try again.")]
+    ProofOfWorkFailed,
+}
+
+/// What the subscribe form sends back besides the subscriber's details.
+pub struct BotCheck<'a> {
+    /// The address to subscribe, as submitted.
+    pub email: &'a str,
+    pub honeypot: Option<&'a str>,
+    pub form_token: Option<&'a str>,
+    pub proof_of_work_nonce: Option<&'a str>,
+}
+
+impl BotCheck<'_> {
+    /// Whether the field people can't see has been filled in.
+    pub fn is_honeypot_filled(&self) -> bool {
+        self.honeypot.is_some_and(|value| !value.is_empty())
+    }
+}
+
+pub struct BotProtection {
+    settings: Reloadable<BotProtectionSettings>,
+    hmac_secret: Secret<String>,
+}
+
+impl BotProtection {
+    pub fn new(
+        settings: impl Into<Reloadable<BotProtectionSettings>>,
+        hmac_secret: Secret<String>,
+    ) -> Self {
+        Self {
+            settings: settings.into(),
+            hmac_secret,
+        }
+    }
+
+    pub fn proof_of_work_difficulty(&self) -> u8 {
+        self.settings.get().proof_of_work_difficulty
+    }
+
+    /// A token recording that a form was rendered now, to embed in it.
+    ///
+    /// Together with the submitted address, it makes the proof-of-work challenge.
+    pub fn issue_form_token(&self) -> String {
+        self.form_token(chrono::Utc::now().timestamp())
+    }
+
+    fn form_token(&self, issued_at: i64) -> String {
+        let signature = self.mac(issued_at).finalize().into_bytes();
+        format!("{}.{}", issued_at, hex::encode(signature))
+    }
+
+    fn mac(&self, issued_at: i64) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(self.hmac_secret.expose_secret().as_bytes()).unwrap();
+        mac.update(format!("subscribe_form:{}", issued_at).as_bytes());
+        mac
+    }
+
+    /// Check the form token and the proof of work of a submission.
+    ///
+    /// The honeypot is left to the caller, which should pretend to succeed
+    /// rather than tell a bot what gave it away.
+    pub fn check(&self, submission: &BotCheck) -> Result<(), BotCheckError> {
+        self.check_at(submission, chrono::Utc::now().timestamp())
+    }
+
+    fn check_at(&self, submission: &BotCheck, now: i64) -> Result<(), BotCheckError> {
+        let form_token = submission
+            .form_token
+            .ok_or(BotCheckError::InvalidFormToken)?;
+        let issued_at = self.verify_form_token(form_token)?;
+        let settings = self.settings.get();
+        let age = now.saturating_sub(issued_at);
+        if age < 0 || (age as u64) < settings.min_fill_seconds {
+            return Err(BotCheckError::SubmittedTooQuickly);
+        }
+        if age as u64 > settings.max_form_age_seconds {
+            return Err(BotCheckError::FormExpired);
+        }
+
+        let difficulty = settings.proof_of_work_difficulty;
+        if difficulty > 0 {
+            let nonce = submission
+                .proof_of_work_nonce
+                .ok_or(BotCheckError::ProofOfWorkFailed)?;
+            let challenge = proof_of_work_challenge(form_token, submission.email);
+            if !is_proof_of_work_valid(&challenge, nonce, difficulty) {
+                return Err(BotCheckError::ProofOfWorkFailed);
+            }
+        }
+        Ok(())
+    }
+
+    /// The time `form_token` was issued at, if we signed it.
+    fn verify_form_token(&self, form_token: &str) -> Result<i64, BotCheckError> {
+        let (issued_at, signature) = form_token
+            .split_once('.')
+            .ok_or(BotCheckError::InvalidFormToken)?;
+        let issued_at: i64 = issued_at
+            .parse()
+            .map_err(|_| BotCheckError::InvalidFormToken)?;
+        let signature = hex::decode(signature).map_err(|_| BotCheckError::InvalidFormToken)?;
+        self.mac(issued_at)
+            .verify_slice(&signature)
+            .map_err(|_| BotCheckError::InvalidFormToken)?;
+        Ok(issued_at)
+    }
+}
+
+/// What the browser hashes with its nonces for a submission.
+fn proof_of_work_challenge(form_token: &str, email: &str) -> String {
+    format!("{}:{}", form_token, email)
+}
+
+/// Whether SHA-256 of `{challenge}:{nonce}` starts with `difficulty` zero bits.
+fn is_proof_of_work_valid(challenge: &str, nonce: &str, difficulty: u8) -> bool {
+    let digest = Sha256::digest(format!("{}:{}", challenge, nonce).as_bytes());
+    leading_zero_bits(&digest) >= u32::from(difficulty)
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_proof_of_work_valid, leading_zero_bits, proof_of_work_challenge, BotCheck,
+        BotCheckError, BotProtection,
+    };
+    use crate::configuration::BotProtectionSettings;
+    use claims::{assert_err_eq, assert_ok};
+    use secrecy::Secret;
+
+    const NOW: i64 = 1_760_000_000;
+
+    fn bot_protection(proof_of_work_difficulty: u8) -> BotProtection {
+        BotProtection::new(
+            BotProtectionSettings {
+                min_fill_seconds: 3,
+                max_form_age_seconds: 3600,
+                proof_of_work_difficulty,
+            },
+            Secret::new("a-secret-for-the-tests".into()),
+        )
+    }
+
+    fn submission(form_token: &str) -> BotCheck<'_> {
+        BotCheck {
+            email: "ursula_le_guin@gmail.com",
+            honeypot: None,
+            form_token: Some(form_token),
+            proof_of_work_nonce: None,
+        }
+    }
+
+    fn solve(challenge: &str, difficulty: u8) -> String {
+        (0u64..)
+            .map(|nonce| nonce.to_string())
+            .find(|nonce| is_proof_of_work_valid(challenge, nonce, difficulty))
+            .unwrap()
+    }
+
+    #[test]
+    fn a_form_filled_in_at_a_human_pace_is_accepted() {
+        let protection = bot_protection(0);
+        let token = protection.form_token(NOW - 10);
+        assert_ok!(protection.check_at(&submission(&token), NOW));
+    }
+
+    #[test]
+    fn a_form_submitted_too_quickly_is_rejected() {
+        let protection = bot_protection(0);
+        let token = protection.form_token(NOW - 1);
+        assert_err_eq!(
+            protection.check_at(&submission(&token), NOW),
+            BotCheckError::SubmittedTooQuickly
+        );
+    }
+
+    #[test]
+    fn a_form_from_the_future_is_rejected() {
+        let protection = bot_protection(0);
+        let token = protection.form_token(NOW + 60);
+        assert_err_eq!(
+            protection.check_at(&submission(&token), NOW),
+            BotCheckError::SubmittedTooQuickly
+        );
+    }
+
+    #[test]
+    fn an_old_form_is_rejected() {
+        let protection = bot_protection(0);
+        let token = protection.form_token(NOW - 3601);
+        assert_err_eq!(
+            protection.check_at(&submission(&token), NOW),
+            BotCheckError::FormExpired
+        );
+    }
+
+    #[test]
+    fn a_missing_or_tampered_form_token_is_rejected() {
+        let protection = bot_protection(0);
+        let token = protection.form_token(NOW - 10);
+        let (_, signature) = token.split_once('.').unwrap();
+        let backdated = format!("{}.{}", NOW - 20, signature);
+
+        for form_token in [None, Some("garbage"), Some(backdated.as_str())] {
+            let submission = BotCheck {
+                form_token,
+                ..submission(&token)
+            };
+            assert_err_eq!(
+                protection.check_at(&submission, NOW),
+                BotCheckError::InvalidFormToken
+            );
+        }
+    }
+
+    #[test]
+    fn a_form_token_signed_with_another_secret_is_rejected() {
+        let token = bot_protection(0).form_token(NOW - 10);
+        let protection = BotProtection::new(
+            bot_protection(0).settings,
+            Secret::new("another-secret".into()),
+        );
+        assert_err_eq!(
+            protection.check_at(&submission(&token), NOW),
+            BotCheckError::InvalidFormToken
+        );
+    }
+
+    #[test]
+    fn the_proof_of_work_is_checked_when_enabled() {
+        let protection = bot_protection(8);
+        let token = protection.form_token(NOW - 10);
+        let challenge = proof_of_work_challenge(&token, submission(&token).email);
+        let nonce = solve(&challenge, 8);
+        let wrong_nonce = (0u64..)
+            .map(|nonce| nonce.to_string())
+            .find(|nonce| !is_proof_of_work_valid(&challenge, nonce, 8))
+            .unwrap();
+
+        let solved = BotCheck {
+            proof_of_work_nonce: Some(&nonce),
+            ..submission(&token)
+        };
+        assert_ok!(protection.check_at(&solved, NOW));
+        let unsolved = BotCheck {
+            proof_of_work_nonce: Some(&wrong_nonce),
+            ..submission(&token)
+        };
+        assert_err_eq!(
+            protection.check_at(&unsolved, NOW),
+            BotCheckError::ProofOfWorkFailed
+        );
+        assert_err_eq!(
+            protection.check_at(&submission(&token), NOW),
+            BotCheckError::ProofOfWorkFailed
+        );
+    }
+
+    #[test]
+    fn a_proof_of_work_only_holds_for_the_address_it_was_solved_for() {
+        let protection = bot_protection(8);
+        let token = protection.form_token(NOW - 10);
+        let nonce = solve(
+            &proof_of_work_challenge(&token, "ursula_le_guin@gmail.com"),
+            8,
+        );
+        let other_address = BotCheck {
+            email: "another_victim@gmail.com",
+            proof_of_work_nonce: Some(&nonce),
+            ..submission(&token)
+        };
+
+        // One in 256 nonces would pass anyway: this one happens not to.
+        assert!(!is_proof_of_work_valid(
+            &proof_of_work_challenge(&token, other_address.email),
+            &nonce,
+            8
+        ));
+        assert_err_eq!(
+            protection.check_at(&other_address, NOW),
+            BotCheckError::ProofOfWorkFailed
+        );
+    }
+
+    #[test]
+    fn leading_zero_bits_are_counted_across_bytes() {
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x10, 0x00]), 11);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[test]
+    fn the_honeypot_is_only_filled_with_a_value() {
+        let empty = BotCheck {
+            honeypot: Some(""),
+            ..submission("")
+        };
+        let filled = BotCheck {
+            honeypot: Some("https://spam.example"),
+            ..submission("")
+        };
+        assert!(!empty.is_honeypot_filled());
+        assert!(filled.is_honeypot_filled());
+    }
+}
@@ -1,7 +1,6 @@
 //! src/domain/subscription_token.rs
 
-use rand::distributions::Alphanumeric;
-use rand::{thread_rng, Rng};
+use crate::utils::random_token;
 
 #[derive(Debug)]
 pub struct SubscriptionToken(String);
@@ -19,12 +18,7 @@ impl SubscriptionToken {
     }
 
     pub fn generate() -> SubscriptionToken {
-        let mut rng = thread_rng();
-        let s = std::iter::repeat_with(|| rng.sample(Alphanumeric))
-            .map(char::from)
-            .take(25)
-            .collect();
-        Self::parse(s).unwrap()
+        Self::parse(random_token(25)).unwrap()
     }
 }
 
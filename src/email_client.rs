@@ -1,9 +1,14 @@
 //! src/email_client.rs
 
 use crate::domain::SubscriberEmail;
+use crate::metrics::metrics;
+use crate::reload::Reloadable;
+use crate::request_id::RequestId;
+use crate::telemetry::trace_context_headers;
 use crate::utils::error_chain_fmt;
 use reqwest::Client;
 use secrecy::{ExposeSecret, Secret};
+use std::time::Duration;
 use url::ParseError;
 
 #[derive(thiserror::Error)]
@@ -25,6 +30,7 @@ pub struct EmailClient {
     base_url: String,
     sender: SubscriberEmail,
     authorization_token: Secret<String>,
+    timeout: Reloadable<Duration>,
 }
 
 impl EmailClient {
@@ -32,14 +38,14 @@ impl EmailClient {
         base_url: String,
         sender: SubscriberEmail,
         authorization_token: Secret<String>,
-        timeout: std::time::Duration,
+        timeout: impl Into<Reloadable<Duration>>,
     ) -> Self {
-        let http_client = Client::builder().timeout(timeout).build().unwrap();
         Self {
-            http_client,
+            http_client: Client::new(),
             base_url,
             sender,
             authorization_token,
+            timeout: timeout.into(),
         }
     }
     pub async fn send_email(
@@ -56,23 +62,45 @@ impl EmailClient {
             subject,
             html_body: html_content,
             text_body: text_content,
+            metadata: RequestId::current().map(|request_id| EmailMetadata {
+                request_id: request_id.to_string(),
+            }),
         };
 
-        let _ = self
+        let outcome = self
             .http_client
             .post(url.as_str())
+            .timeout(*self.timeout.get())
+            .headers(trace_context_headers())
             .header(
                 "X-Postmark-Server-Token",
                 self.authorization_token.expose_secret(),
             )
             .json(&request_body)
             .send()
-            .await?
-            .error_for_status()?;
+            .await
+            .and_then(|response| response.error_for_status());
+        metrics().record_email_sent(outcome.is_ok());
+        outcome?;
 
         Ok(())
     }
 
+    /// Check that the email provider's API answers, whatever it answers
+    /// short of a server error.
+    pub async fn ping(&self) -> Result<(), reqwest::Error> {
+        let response = self
+            .http_client
+            .get(&self.base_url)
+            .timeout(*self.timeout.get())
+            .send()
+            .await?;
+        if response.status().is_server_error() {
+            response.error_for_status()?;
+        }
+        Ok(())
+    }
+
     pub fn build_url(&self) -> Result<reqwest::Url, ParseError> {
         reqwest::Url::parse(&self.base_url)?.join("email")
     }
@@ -86,6 +114,14 @@ struct SendEmailRequest<'a> {
     subject: &'a str,
     html_body: &'a str,
     text_body: &'a str,
+    /// Shown next to the message in the provider's activity log.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<EmailMetadata>,
+}
+
+#[derive(serde::Serialize)]
+struct EmailMetadata {
+    request_id: String,
 }
 
 #[cfg(test)]
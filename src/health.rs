@@ -0,0 +1,143 @@
+//! src/health.rs
+//!
+//! Checks of the services the application depends on, so that the load
+//! balancer takes instances that can't reach them out of rotation.
+
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::future::Future;
+use std::time::Instant;
+
+use crate::configuration::HealthCheckSettings;
+use crate::email_client::EmailClient;
+use crate::reload::Reloadable;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Up,
+    Down,
+}
+
+#[derive(serde::Serialize)]
+pub struct DependencyReport {
+    pub status: Status,
+    pub latency_ms: u64,
+}
+
+#[derive(serde::Serialize)]
+pub struct ReadinessReport {
+    pub status: Status,
+    pub postgres: DependencyReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redis: Option<DependencyReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_provider: Option<DependencyReport>,
+}
+
+impl ReadinessReport {
+    pub fn is_ready(&self) -> bool {
+        matches!(self.status, Status::Up)
+    }
+}
+
+pub struct ReadinessChecks {
+    settings: Reloadable<HealthCheckSettings>,
+    /// Only set when sessions are kept in Redis.
+    redis: Option<redis::Client>,
+}
+
+impl ReadinessChecks {
+    pub fn new(
+        settings: impl Into<Reloadable<HealthCheckSettings>>,
+        redis_uri: Option<&Secret<String>>,
+    ) -> Result<Self, anyhow::Error> {
+        let redis = redis_uri
+            .map(|redis_uri| redis::Client::open(redis_uri.expose_secret().as_str()))
+            .transpose()
+            .context("Invalid `redis_uri`.")?;
+        Ok(Self {
+            settings: settings.into(),
+            redis,
+        })
+    }
+
+    /// Check every dependency at once, each within the configured timeout.
+    #[tracing::instrument(name = "Check readiness", skip_all)]
+    pub async fn check(&self, pool: &PgPool, email_client: &EmailClient) -> ReadinessReport {
+        let settings = self.settings.get();
+        let (postgres, redis, email_provider) = tokio::join!(
+            self.check_dependency("postgres", ping_postgres(pool)),
+            async {
+                match &self.redis {
+                    Some(client) => Some(self.check_dependency("redis", ping_redis(client)).await),
+                    None => None,
+                }
+            },
+            async {
+                if settings.check_email_provider {
+                    let ping = async { email_client.ping().await.map_err(Into::into) };
+                    Some(self.check_dependency("email_provider", ping).await)
+                } else {
+                    None
+                }
+            },
+        );
+        let is_up = |report: &DependencyReport| matches!(report.status, Status::Up);
+        let status = if is_up(&postgres)
+            && redis.as_ref().is_none_or(is_up)
+            && email_provider.as_ref().is_none_or(is_up)
+        {
+            Status::Up
+        } else {
+            Status::Down
+        };
+        ReadinessReport {
+            status,
+            postgres,
+            redis,
+            email_provider,
+        }
+    }
+
+    async fn check_dependency(
+        &self,
+        name: &str,
+        ping: impl Future<Output = Result<(), anyhow::Error>>,
+    ) -> DependencyReport {
+        let started_at = Instant::now();
+        let outcome = tokio::time::timeout(self.settings.get().timeout(), ping)
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Timed out.")));
+        let latency_ms = started_at.elapsed().as_millis() as u64;
+        let status = match outcome {
+            Ok(()) => Status::Up,
+            Err(e) => {
+                tracing::warn!(error.cause_chain = ?e, "{} is unavailable.", name);
+                Status::Down
+            }
+        };
+        DependencyReport { status, latency_ms }
+    }
+}
+
+async fn ping_postgres(pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!("SELECT 1 AS ping")
+        .fetch_one(pool)
+        .await
+        .context("Failed to query Postgres.")?;
+    Ok(())
+}
+
+async fn ping_redis(client: &redis::Client) -> Result<(), anyhow::Error> {
+    let mut connection = client
+        .get_multiplexed_async_connection()
+        .await
+        .context("Failed to connect to Redis.")?;
+    let _: String = redis::cmd("PING")
+        .query_async(&mut connection)
+        .await
+        .context("Failed to ping Redis.")?;
+    Ok(())
+}
@@ -1,11 +1,22 @@
 //! src/lib.rs
 
+pub mod audit;
 pub mod authentication;
+pub mod bootstrap;
+pub mod bot_protection;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
+pub mod health;
+pub mod metrics;
+pub mod rate_limit;
+pub mod reload;
+pub mod request_id;
 pub mod routes;
+pub mod session_lifetime;
 pub mod session_state;
+pub mod session_store;
+pub mod shutdown;
 pub mod startup;
 pub mod telemetry;
 pub mod utils;
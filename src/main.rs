@@ -1,16 +1,54 @@
 //! main.rs
 
-use zero2prod::configuration::get_configuration;
+use clap::Parser;
+use std::path::PathBuf;
+use zero2prod::configuration::load_configuration;
+use zero2prod::shutdown::wait_for_signal;
 use zero2prod::startup::Application;
-use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::telemetry::{get_subscriber, get_tracer_provider, init_subscriber};
+
+#[derive(Parser)]
+#[command(name = "zero2prod", about = "Serve the zero2prod newsletter")]
+struct Cli {
+    /// A YAML file applied over the configuration of `APP_ENVIRONMENT`.
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    let subscriber = get_subscriber("zero2prod".into(), "info".into(), std::io::stdout);
+    let cli = Cli::parse();
+    let configuration =
+        load_configuration(cli.config.as_deref()).expect("Failed to read configuration");
+
+    let tracer_provider = configuration
+        .opentelemetry
+        .as_ref()
+        .map(|settings| get_tracer_provider("zero2prod".into(), settings))
+        .transpose()?;
+    let (subscriber, log_filter) = get_subscriber(
+        "zero2prod".into(),
+        configuration.log_filter.clone(),
+        std::io::stdout,
+        tracer_provider.as_ref(),
+    );
     init_subscriber(subscriber);
 
-    let configuration = get_configuration().expect("Failed to read configuration");
-    let application = Application::build(configuration).await?;
-    application.run_until_stopped().await?;
+    let config_file = cli.config;
+    let load_settings = Box::new(move || Ok(load_configuration(config_file.as_deref())?));
+    let application = Application::build(configuration, load_settings, Some(log_filter)).await?;
+    let shutdown = application.shutdown_token();
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        shutdown.cancel();
+    });
+    let outcome = application.run_until_stopped().await;
+    if let Some(tracer_provider) = tracer_provider {
+        // Flush the spans still waiting to be exported.
+        if let Err(e) = tracer_provider.shutdown() {
+            eprintln!("Failed to shut down the tracer provider: {}", e);
+        }
+    }
+    outcome?;
     Ok(())
 }
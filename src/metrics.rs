@@ -0,0 +1,253 @@
+//! src/metrics.rs
+//!
+//! Prometheus metrics, served in the text exposition format at `/metrics`.
+//!
+//! They give away routes and traffic: they are only served on a port of their
+//! own, kept off the public network, or to scrapers presenting a token.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::http::header::ContentType;
+use actix_web::middleware::Next;
+use actix_web::{web, HttpRequest, HttpResponse};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use crate::authentication::middleware::{bearer_token, constant_time_eq};
+use crate::utils::e500;
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// The metrics of the process, shared by everything that records them.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    emails_sent_total: IntCounterVec,
+    newsletter_deliveries_total: IntCounterVec,
+    newsletter_delivery_duration_seconds: Histogram,
+    password_verification_duration_seconds: Histogram,
+    session_store_errors_total: IntCounterVec,
+}
+
+/// What became of one email of a newsletter issue.
+#[derive(Copy, Clone, Debug)]
+pub enum DeliveryOutcome {
+    Sent,
+    Failed,
+    /// The subscriber's stored details are invalid.
+    Skipped,
+}
+
+impl DeliveryOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryOutcome::Sent => "sent",
+            DeliveryOutcome::Failed => "failed",
+            DeliveryOutcome::Skipped => "skipped",
+        }
+    }
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("zero2prod".into()), None)
+            .expect("The metrics prefix is valid.");
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled."),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests.",
+            ),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new(
+                "db_pool_connections",
+                "Connections of the Postgres pool, by state.",
+            ),
+            &["state"],
+        )
+        .unwrap();
+        let emails_sent_total = IntCounterVec::new(
+            Opts::new("emails_sent_total", "Emails handed to the email provider."),
+            &["outcome"],
+        )
+        .unwrap();
+        let newsletter_deliveries_total = IntCounterVec::new(
+            Opts::new(
+                "newsletter_deliveries_total",
+                "Newsletter emails, one per issue and subscriber.",
+            ),
+            &["outcome"],
+        )
+        .unwrap();
+        let newsletter_delivery_duration_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "newsletter_delivery_duration_seconds",
+                "Time taken to deliver a newsletter issue to every subscriber.",
+            )
+            .buckets(vec![0.1, 0.5, 1.0, 5.0, 15.0, 60.0, 300.0, 900.0]),
+        )
+        .unwrap();
+        let password_verification_duration_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "password_verification_duration_seconds",
+                "Time taken to verify a password against its Argon2 hash.",
+            )
+            .buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0]),
+        )
+        .unwrap();
+        let session_store_errors_total = IntCounterVec::new(
+            Opts::new(
+                "session_store_errors_total",
+                "Errors returned by the session store.",
+            ),
+            &["operation"],
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(http_requests_total.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration_seconds.clone()),
+            Box::new(db_pool_connections.clone()),
+            Box::new(emails_sent_total.clone()),
+            Box::new(newsletter_deliveries_total.clone()),
+            Box::new(newsletter_delivery_duration_seconds.clone()),
+            Box::new(password_verification_duration_seconds.clone()),
+            Box::new(session_store_errors_total.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("Metrics are registered once.");
+        }
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            db_pool_connections,
+            emails_sent_total,
+            newsletter_deliveries_total,
+            newsletter_delivery_duration_seconds,
+            password_verification_duration_seconds,
+            session_store_errors_total,
+        }
+    }
+
+    fn record_http_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests_total.with_label_values(&labels).inc();
+        self.http_request_duration_seconds
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_email_sent(&self, succeeded: bool) {
+        let outcome = if succeeded { "sent" } else { "failed" };
+        self.emails_sent_total.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_newsletter_delivery(&self, outcome: DeliveryOutcome) {
+        self.newsletter_deliveries_total
+            .with_label_values(&[outcome.as_str()])
+            .inc();
+    }
+
+    pub fn observe_newsletter_delivery_duration(&self, elapsed: Duration) {
+        self.newsletter_delivery_duration_seconds
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_password_verification(&self, elapsed: Duration) {
+        self.password_verification_duration_seconds
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_session_store_error(&self, operation: &str) {
+        self.session_store_errors_total
+            .with_label_values(&[operation])
+            .inc();
+    }
+
+    /// All metrics in the Prometheus text format, with the pool's current state.
+    fn encode(&self, pool: &PgPool) -> Result<String, anyhow::Error> {
+        let size = i64::from(pool.size());
+        let idle = pool.num_idle() as i64;
+        let max = i64::from(pool.options().get_max_connections());
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(size - idle);
+        self.db_pool_connections
+            .with_label_values(&["max"])
+            .set(max);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// The bearer token scrapers must present, when one is configured.
+pub struct MetricsToken(pub Secret<String>);
+
+pub async fn metrics_endpoint(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    token: Option<web::Data<MetricsToken>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Some(token) = token {
+        let presented = bearer_token(request.headers());
+        if !presented.is_some_and(|presented| constant_time_eq(&presented, token.0.expose_secret()))
+        {
+            return Ok(HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, r#"Bearer realm="metrics""#))
+                .finish());
+        }
+    }
+    let body = metrics().encode(&pool).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType(
+            TextEncoder::new().format_type().parse().unwrap(),
+        ))
+        .body(body))
+}
+
+/// Count and time every request, by route pattern rather than path so that
+/// IDs in paths don't create a series each.
+pub async fn record_http_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started_at = Instant::now();
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".into());
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(response) => response.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    metrics().record_http_request(&method, &route, status.as_u16(), started_at.elapsed());
+    result
+}
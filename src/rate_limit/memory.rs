@@ -0,0 +1,71 @@
+//! src/rate_limit/memory.rs
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{take_token, RateLimitDecision};
+use crate::configuration::RateLimit;
+
+/// How often buckets that have filled up again are forgotten.
+const SWEEP_PERIOD: Duration = Duration::from_secs(60);
+
+/// Keeps buckets in the memory of the process, so each instance limits
+/// clients on its own.
+#[derive(Clone)]
+pub struct MemoryRateLimitStore {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    buckets: HashMap<String, Bucket>,
+    next_sweep: Instant,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// A full bucket is the same as no bucket at all.
+    full_at: Instant,
+}
+
+impl Default for MemoryRateLimitStore {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                buckets: HashMap::new(),
+                next_sweep: Instant::now() + SWEEP_PERIOD,
+            })),
+        }
+    }
+}
+
+impl MemoryRateLimitStore {
+    pub fn take(&self, key: &str, limit: RateLimit) -> RateLimitDecision {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        if state.next_sweep <= now {
+            state.buckets.retain(|_, bucket| bucket.full_at > now);
+            state.next_sweep = now + SWEEP_PERIOD;
+        }
+        let (tokens, elapsed) = match state.buckets.get(key) {
+            Some(bucket) => (
+                bucket.tokens,
+                now.duration_since(bucket.updated_at).as_secs_f64(),
+            ),
+            None => (f64::from(limit.capacity), 0.0),
+        };
+        let (tokens, decision) = take_token(tokens, elapsed, limit);
+        let missing = (f64::from(limit.capacity) - tokens).max(0.0);
+        let full_at = now + Duration::from_secs_f64(missing * limit.refill_seconds.max(1) as f64);
+        state.buckets.insert(
+            key.to_owned(),
+            Bucket {
+                tokens,
+                updated_at: now,
+                full_at,
+            },
+        );
+        decision
+    }
+}
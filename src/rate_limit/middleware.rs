@@ -0,0 +1,93 @@
+//! src/rate_limit/middleware.rs
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{RETRY_AFTER, X_FORWARDED_FOR};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpResponse};
+use std::net::IpAddr;
+use std::time::Duration;
+
+use super::{RateLimitDecision, RateLimiter};
+use crate::configuration::{RateLimit, RateLimitSettings};
+use crate::utils::e500;
+
+/// Limit login attempts per client IP.
+pub async fn rate_limit_login(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    rate_limit_by_ip(req, next, "login", |settings| settings.login).await
+}
+
+/// Limit subscriptions per client IP.
+pub async fn rate_limit_subscriptions(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    rate_limit_by_ip(req, next, "subscriptions", |settings| {
+        settings.subscriptions
+    })
+    .await
+}
+
+/// Limit subscription confirmations per client IP.
+pub async fn rate_limit_subscriptions_confirm(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    rate_limit_by_ip(req, next, "subscriptions_confirm", |settings| {
+        settings.subscriptions_confirm
+    })
+    .await
+}
+
+async fn rate_limit_by_ip<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+    route: &str,
+    limit: impl FnOnce(&RateLimitSettings) -> RateLimit,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    let rate_limiter = req
+        .app_data::<web::Data<RateLimiter>>()
+        .ok_or_else(|| e500("The rate limiter is not registered."))?;
+    let settings = rate_limiter.settings();
+    let client_ip = client_ip(&req, settings.use_forwarded_headers)
+        .map_or_else(|| "unknown".into(), |ip| ip.to_string());
+
+    let key = format!("{}:ip:{}", route, client_ip);
+    match rate_limiter.check(&key, limit(&settings)).await {
+        RateLimitDecision::Allowed => next.call(req).await,
+        RateLimitDecision::Limited { retry_after } => {
+            let e = anyhow::anyhow!("The client went over the rate limit of {}.", route);
+            Err(InternalError::from_response(e, too_many_requests(retry_after)).into())
+        }
+    }
+}
+
+/// The address of the client, without the port, which changes with every connection.
+///
+/// With `use_forwarded_headers`, it is the last `X-Forwarded-For` entry: the
+/// one our proxy appended. The client can put anything in the entries before.
+fn client_ip(req: &ServiceRequest, use_forwarded_headers: bool) -> Option<IpAddr> {
+    let forwarded_for = use_forwarded_headers
+        .then(|| {
+            req.headers()
+                .get_all(X_FORWARDED_FOR)
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .last()
+                .and_then(|ip| ip.trim().parse().ok())
+        })
+        .flatten();
+    forwarded_for.or_else(|| req.peer_addr().map(|address| address.ip()))
+}
+
+/// A 429 telling the client how many seconds to wait before trying again.
+pub fn too_many_requests(retry_after: Duration) -> HttpResponse {
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, seconds.to_string()))
+        .body("Too many requests - please try again later.")
+}
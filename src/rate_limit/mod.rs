@@ -0,0 +1,152 @@
+//! src/rate_limit/mod.rs
+//!
+//! Token buckets limiting how often clients may call the public endpoints.
+mod memory;
+mod middleware;
+mod redis;
+
+pub use self::redis::RedisRateLimitStore;
+pub use memory::MemoryRateLimitStore;
+pub use middleware::{
+    rate_limit_login, rate_limit_subscriptions, rate_limit_subscriptions_confirm, too_many_requests,
+};
+
+use secrecy::Secret;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::configuration::{RateLimit, RateLimitSettings, RateLimitStoreKind};
+use crate::reload::Reloadable;
+
+/// Whether a request may go ahead.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RateLimitDecision {
+    Allowed,
+    Limited { retry_after: Duration },
+}
+
+/// Takes tokens from the buckets in the configured store.
+#[derive(Clone)]
+pub struct RateLimiter {
+    settings: Reloadable<RateLimitSettings>,
+    redis: Option<RedisRateLimitStore>,
+    memory: MemoryRateLimitStore,
+}
+
+impl RateLimiter {
+    /// A rate limiter keeping its buckets in the store `settings` picks.
+    ///
+    /// When Redis can't be reached, each instance limits clients on its own.
+    /// Only the limits are reloaded: the store is the one picked here.
+    pub async fn new(
+        settings: impl Into<Reloadable<RateLimitSettings>>,
+        redis_uri: Option<&Secret<String>>,
+    ) -> Self {
+        let settings = settings.into();
+        let redis = match (settings.get().store, redis_uri) {
+            (RateLimitStoreKind::Memory, _) => None,
+            (RateLimitStoreKind::Redis, None) => {
+                tracing::warn!("`redis_uri` is not set: rate limiting falls back to memory.");
+                None
+            }
+            (RateLimitStoreKind::Redis, Some(redis_uri)) => {
+                match RedisRateLimitStore::connect(redis_uri).await {
+                    Ok(store) => Some(store),
+                    Err(e) => {
+                        tracing::warn!(
+                            error.cause_chain = ?e,
+                            "Failed to connect to Redis: rate limiting falls back to memory."
+                        );
+                        None
+                    }
+                }
+            }
+        };
+        Self {
+            settings,
+            redis,
+            memory: MemoryRateLimitStore::default(),
+        }
+    }
+
+    pub fn settings(&self) -> Arc<RateLimitSettings> {
+        self.settings.get()
+    }
+
+    /// Take a token from the bucket `key`, which holds up to `limit.capacity`.
+    #[tracing::instrument(name = "Check rate limit", skip(self))]
+    pub async fn check(&self, key: &str, limit: RateLimit) -> RateLimitDecision {
+        if let Some(redis) = &self.redis {
+            match redis.take(key, limit).await {
+                Ok(decision) => return decision,
+                Err(e) => tracing::warn!(
+                    error.cause_chain = ?e,
+                    "Failed to rate limit through Redis, falling back to memory."
+                ),
+            }
+        }
+        self.memory.take(key, limit)
+    }
+}
+
+/// Refill a bucket that held `tokens` for the `elapsed` seconds since it was
+/// last used, then take a token from it.
+///
+/// Returns the tokens left in the bucket along with the decision.
+fn take_token(tokens: f64, elapsed: f64, limit: RateLimit) -> (f64, RateLimitDecision) {
+    let capacity = f64::from(limit.capacity);
+    let refill_seconds = limit.refill_seconds.max(1) as f64;
+    let tokens = (tokens + elapsed.max(0.0) / refill_seconds).min(capacity);
+    if tokens >= 1.0 {
+        (tokens - 1.0, RateLimitDecision::Allowed)
+    } else {
+        let retry_after = Duration::from_secs_f64((1.0 - tokens) * refill_seconds);
+        (tokens, RateLimitDecision::Limited { retry_after })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{take_token, RateLimitDecision};
+    use crate::configuration::RateLimit;
+    use std::time::Duration;
+
+    const LIMIT: RateLimit = RateLimit {
+        capacity: 2,
+        refill_seconds: 10,
+    };
+
+    #[test]
+    fn a_full_bucket_allows_a_burst_of_its_capacity() {
+        let (tokens, decision) = take_token(2.0, 0.0, LIMIT);
+        assert_eq!(decision, RateLimitDecision::Allowed);
+        let (tokens, decision) = take_token(tokens, 0.0, LIMIT);
+        assert_eq!(decision, RateLimitDecision::Allowed);
+        let (_, decision) = take_token(tokens, 0.0, LIMIT);
+        assert_eq!(
+            decision,
+            RateLimitDecision::Limited {
+                retry_after: Duration::from_secs(10)
+            }
+        );
+    }
+
+    #[test]
+    fn an_empty_bucket_refills_over_time() {
+        let (tokens, decision) = take_token(0.0, 5.0, LIMIT);
+        assert_eq!(
+            decision,
+            RateLimitDecision::Limited {
+                retry_after: Duration::from_secs(5)
+            }
+        );
+        let (_, decision) = take_token(tokens, 5.0, LIMIT);
+        assert_eq!(decision, RateLimitDecision::Allowed);
+    }
+
+    #[test]
+    fn a_bucket_never_holds_more_than_its_capacity() {
+        let (tokens, _) = take_token(0.0, 1000.0, LIMIT);
+        assert_eq!(tokens, 1.0);
+    }
+}
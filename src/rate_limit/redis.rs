@@ -0,0 +1,87 @@
+//! src/rate_limit/redis.rs
+
+use anyhow::Context;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::Script;
+use secrecy::{ExposeSecret, Secret};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::RateLimitDecision;
+use crate::configuration::RateLimit;
+
+/// `take_token`, run atomically by Redis.
+///
+/// Returns the number of milliseconds to wait before retrying, 0 if the
+/// request is allowed.
+static TAKE_TOKEN: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+        local capacity = tonumber(ARGV[1])
+        local refill_seconds = tonumber(ARGV[2])
+        local now = tonumber(ARGV[3])
+        local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+        local tokens = tonumber(bucket[1]) or capacity
+        local updated_at = tonumber(bucket[2]) or now
+        tokens = math.min(capacity, tokens + math.max(0, now - updated_at) / refill_seconds)
+        local retry_after_ms = 0
+        if tokens >= 1 then
+            tokens = tokens - 1
+        else
+            retry_after_ms = math.ceil((1 - tokens) * refill_seconds * 1000)
+        end
+        redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', tostring(now))
+        redis.call('EXPIRE', KEYS[1], math.ceil(capacity * refill_seconds))
+        return retry_after_ms
+        "#,
+    )
+});
+
+/// Keeps buckets in Redis, so that all instances share them.
+#[derive(Clone)]
+pub struct RedisRateLimitStore {
+    connection: ConnectionManager,
+}
+
+impl RedisRateLimitStore {
+    pub async fn connect(redis_uri: &Secret<String>) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(redis_uri.expose_secret().as_str())
+            .context("Invalid Redis URI.")?;
+        // Requests wait on Redis: give up quickly and fall back to memory.
+        let config = ConnectionManagerConfig::new()
+            .set_number_of_retries(1)
+            .set_connection_timeout(Duration::from_secs(1))
+            .set_response_timeout(Duration::from_millis(500));
+        let connection = client
+            .get_connection_manager_with_config(config)
+            .await
+            .context("Failed to connect to Redis.")?;
+        Ok(Self { connection })
+    }
+
+    pub async fn take(
+        &self,
+        key: &str,
+        limit: RateLimit,
+    ) -> Result<RateLimitDecision, anyhow::Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("The system clock is set before 1970.")?
+            .as_secs_f64();
+        let mut connection = self.connection.clone();
+        let retry_after_ms: u64 = TAKE_TOKEN
+            .key(format!("rate_limit:{}", key))
+            .arg(limit.capacity)
+            .arg(limit.refill_seconds.max(1))
+            .arg(now)
+            .invoke_async(&mut connection)
+            .await
+            .context("Failed to take a token from a Redis bucket.")?;
+        Ok(match retry_after_ms {
+            0 => RateLimitDecision::Allowed,
+            ms => RateLimitDecision::Limited {
+                retry_after: Duration::from_millis(ms),
+            },
+        })
+    }
+}
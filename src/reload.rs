@@ -0,0 +1,334 @@
+//! src/reload.rs
+//!
+//! Settings that can change without a restart. The configuration is read
+//! again on SIGHUP or from the admin area, and the new values are swapped in
+//! for the requests that start afterwards.
+
+use anyhow::Context;
+use arc_swap::ArcSwap;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::configuration::{
+    BotProtectionSettings, HealthCheckSettings, RateLimitSettings, Settings,
+};
+use crate::telemetry::LogFilter;
+
+/// The settings a reload applies, with everything under them.
+///
+/// Any other change is only picked up by a restart.
+const RELOADABLE_KEYS: [&str; 9] = [
+    "log_filter",
+    "email_client.timeout_milliseconds",
+    "rate_limiting.use_forwarded_headers",
+    "rate_limiting.login",
+    "rate_limiting.subscriptions",
+    "rate_limiting.subscriptions_per_email",
+    "rate_limiting.subscriptions_confirm",
+    "bot_protection",
+    "health_check",
+];
+
+/// A value that a `SettingsReloader` may replace at any time.
+pub struct Reloadable<T>(Arc<ArcSwap<T>>);
+
+impl<T> Reloadable<T> {
+    /// The current value, which later reloads leave as it is.
+    pub fn get(&self) -> Arc<T> {
+        self.0.load_full()
+    }
+
+    fn set(&self, value: T) {
+        self.0.store(Arc::new(value));
+    }
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> From<T> for Reloadable<T> {
+    fn from(value: T) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(value)))
+    }
+}
+
+/// Reads the configuration again, e.g. from the files it was first read from.
+pub type LoadSettings = Box<dyn Fn() -> Result<Settings, anyhow::Error> + Send + Sync>;
+
+/// What a reload changed.
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    /// The keys of the settings that changed and are now in use.
+    pub applied: Vec<String>,
+    /// The keys of the settings that changed but need a restart to apply.
+    pub requires_restart: Vec<String>,
+}
+
+impl ReloadReport {
+    pub fn audit_details(&self) -> serde_json::Value {
+        serde_json::json!({
+            "applied": self.applied,
+            "requires_restart": self.requires_restart,
+        })
+    }
+}
+
+pub struct SettingsReloader {
+    load: LoadSettings,
+    /// The settings in use: the ones the application started with, as
+    /// updated by reloads since.
+    running: Mutex<Settings>,
+    email_timeout: Reloadable<Duration>,
+    rate_limiting: Reloadable<RateLimitSettings>,
+    bot_protection: Reloadable<BotProtectionSettings>,
+    health_check: Reloadable<HealthCheckSettings>,
+    /// Missing when something else owns the global subscriber, as in tests.
+    log_filter: Option<LogFilter>,
+}
+
+impl SettingsReloader {
+    pub fn new(settings: Settings, load: LoadSettings, log_filter: Option<LogFilter>) -> Self {
+        Self {
+            load,
+            email_timeout: settings.email_client.timeout().into(),
+            rate_limiting: settings.rate_limiting.clone().into(),
+            bot_protection: settings.bot_protection.clone().into(),
+            health_check: settings.health_check.clone().into(),
+            running: Mutex::new(settings),
+            log_filter,
+        }
+    }
+
+    pub fn email_timeout(&self) -> Reloadable<Duration> {
+        self.email_timeout.clone()
+    }
+
+    pub fn rate_limiting(&self) -> Reloadable<RateLimitSettings> {
+        self.rate_limiting.clone()
+    }
+
+    pub fn bot_protection(&self) -> Reloadable<BotProtectionSettings> {
+        self.bot_protection.clone()
+    }
+
+    pub fn health_check(&self) -> Reloadable<HealthCheckSettings> {
+        self.health_check.clone()
+    }
+
+    /// The filter of the application's logs, unless something else set them up.
+    pub fn log_filter(&self) -> Option<&LogFilter> {
+        self.log_filter.as_ref()
+    }
+
+    /// The values of the reloadable settings in use, by key.
+    pub fn reloadable_values(&self) -> Vec<(String, String)> {
+        flatten(&self.running.lock().unwrap())
+            .into_iter()
+            .filter(|(key, _)| is_reloadable(key))
+            .map(|(key, value)| match value {
+                serde_json::Value::String(value) => (key, value),
+                value => (key, value.to_string()),
+            })
+            .collect()
+    }
+
+    /// Read the configuration again and apply what can be without a restart.
+    ///
+    /// Nothing is applied unless the whole configuration is valid.
+    #[tracing::instrument(name = "Reload settings", skip(self))]
+    pub fn reload(&self) -> Result<ReloadReport, anyhow::Error> {
+        let loaded = (self.load)().context("Failed to read the configuration.")?;
+        loaded.validate()?;
+
+        let mut running = self.running.lock().unwrap();
+        let (applied, requires_restart): (Vec<_>, Vec<_>) = changed_keys(&running, &loaded)
+            .into_iter()
+            .partition(|key| is_reloadable(key));
+        if let Some(log_filter) = &self.log_filter {
+            // `RUST_LOG` keeps precedence until the filter itself is changed.
+            if applied.iter().any(|key| key == "log_filter") {
+                log_filter.set(&loaded.log_filter)?;
+            }
+        }
+        running.log_filter = loaded.log_filter;
+        running.email_client.timeout_milliseconds = loaded.email_client.timeout_milliseconds;
+        running.rate_limiting = RateLimitSettings {
+            store: running.rate_limiting.store,
+            ..loaded.rate_limiting
+        };
+        running.bot_protection = loaded.bot_protection;
+        running.health_check = loaded.health_check;
+        self.email_timeout.set(running.email_client.timeout());
+        self.rate_limiting.set(running.rate_limiting.clone());
+        self.bot_protection.set(running.bot_protection.clone());
+        self.health_check.set(running.health_check.clone());
+
+        if !applied.is_empty() {
+            tracing::info!(?applied, "Applied the reloaded settings.");
+        }
+        if !requires_restart.is_empty() {
+            tracing::warn!(
+                ?requires_restart,
+                "Some settings changed that only a restart applies."
+            );
+        }
+        Ok(ReloadReport {
+            applied,
+            requires_restart,
+        })
+    }
+}
+
+/// Reload the settings every time the process receives SIGHUP, until `shutdown`.
+pub async fn reload_on_hangup(
+    reloader: Arc<SettingsReloader>,
+    pool: PgPool,
+    shutdown: CancellationToken,
+) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                tracing::error!(error.cause_chain = ?e, "Failed to listen for SIGHUP.");
+                return;
+            }
+        };
+        loop {
+            tokio::select! {
+                _ = hangups.recv() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            let report = match reloader.reload() {
+                Ok(report) => report,
+                Err(e) => {
+                    tracing::error!(error.cause_chain = ?e, "Failed to reload the settings.");
+                    continue;
+                }
+            };
+            let event = AuditEvent::without_request(AuditAction::SettingsReloaded, None)
+                .with_details(report.audit_details());
+            if let Err(e) = record_audit_event(&pool, event).await {
+                tracing::error!(error.cause_chain = ?e, "Failed to audit a reload.");
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (reloader, pool);
+        shutdown.cancelled().await;
+    }
+}
+
+fn is_reloadable(key: &str) -> bool {
+    RELOADABLE_KEYS.iter().any(|reloadable| {
+        key.strip_prefix(reloadable)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    })
+}
+
+/// The keys whose values differ, leaving out those under a key that does,
+/// e.g. `oidc.client_id` when `oidc` is turned on.
+fn changed_keys(running: &Settings, loaded: &Settings) -> Vec<String> {
+    let running = flatten(running);
+    let loaded = flatten(loaded);
+    let changed: Vec<&String> = running
+        .keys()
+        .chain(loaded.keys())
+        .filter(|key| running.get(*key) != loaded.get(*key))
+        .collect();
+    let mut keys: Vec<String> = changed
+        .iter()
+        .filter(|key| {
+            !changed
+                .iter()
+                .any(|parent| key.starts_with(&format!("{}.", parent)))
+        })
+        .map(|key| key.to_string())
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Every value in `settings` by its key, e.g. `rate_limiting.login.capacity`.
+fn flatten(settings: &Settings) -> BTreeMap<String, serde_json::Value> {
+    fn walk(
+        prefix: Option<&str>,
+        value: serde_json::Value,
+        values: &mut BTreeMap<String, serde_json::Value>,
+    ) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for (name, value) in fields {
+                    let key = match prefix {
+                        Some(prefix) => format!("{}.{}", prefix, name),
+                        None => name,
+                    };
+                    walk(Some(&key), value, values);
+                }
+            }
+            value => {
+                if let Some(key) = prefix {
+                    values.insert(key.to_owned(), value);
+                }
+            }
+        }
+    }
+
+    let mut values = BTreeMap::new();
+    let settings = serde_json::to_value(settings).expect("Settings always serialize.");
+    walk(None, settings, &mut values);
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{changed_keys, is_reloadable};
+    use crate::configuration::{test_settings, OidcSettings};
+    use secrecy::Secret;
+
+    #[test]
+    fn keys_under_a_reloadable_key_are_reloadable() {
+        assert!(is_reloadable("bot_protection.min_fill_seconds"));
+        assert!(is_reloadable("rate_limiting.subscriptions.capacity"));
+        assert!(!is_reloadable("rate_limiting.store"));
+        assert!(!is_reloadable("health_checks"));
+    }
+
+    #[test]
+    fn changed_secrets_are_found_by_key() {
+        let running = test_settings();
+        let mut loaded = running.clone();
+        loaded.database.password = Secret::new("another-password".into());
+        loaded.email_client.timeout_milliseconds += 1;
+
+        assert_eq!(
+            changed_keys(&running, &loaded),
+            ["database.password", "email_client.timeout_milliseconds"]
+        );
+    }
+
+    #[test]
+    fn a_section_turned_on_is_reported_once() {
+        let mut running = test_settings();
+        running.oidc = None;
+        let mut loaded = running.clone();
+        loaded.oidc = Some(OidcSettings {
+            issuer_url: "https://idp.example.com".into(),
+            client_id: "zero2prod".into(),
+            client_secret: Secret::new("secret".into()),
+        });
+
+        assert_eq!(changed_keys(&running, &loaded), ["oidc"]);
+    }
+}
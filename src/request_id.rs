@@ -0,0 +1,157 @@
+//! src/request_id.rs
+//!
+//! Every request gets an ID that is logged, echoed in the `X-Request-Id`
+//! response header, quoted in error responses and passed on to the email
+//! provider, so that one report can be matched to everything it caused.
+
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage, HttpResponse};
+use tracing::Span;
+use tracing_actix_web::{root_span, DefaultRootSpanBuilder, RootSpanBuilder};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The longest `X-Request-Id` accepted from clients.
+const MAX_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// The ID of the request being handled by the current task, if any.
+    pub fn current() -> Option<RequestId> {
+        CURRENT_REQUEST_ID.try_with(Clone::clone).ok()
+    }
+
+    fn from_header(req: &ServiceRequest) -> Option<Self> {
+        let value = req.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?;
+        let is_valid = !value.is_empty()
+            && value.len() <= MAX_LENGTH
+            && value.chars().all(|c| c.is_ascii_graphic());
+        is_valid.then(|| Self(value.to_owned()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Root spans that record the request ID a client sent, if any.
+///
+/// Requests without one are known by the `request_id` that `TracingLogger`
+/// generates; others log it too, next to theirs as `client_request_id`.
+pub struct RequestIdRootSpanBuilder;
+
+impl RootSpanBuilder for RequestIdRootSpanBuilder {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let span = root_span!(request, client_request_id = tracing::field::Empty);
+        let request_id = match RequestId::from_header(request) {
+            Some(request_id) => {
+                span.record("client_request_id", request_id.as_str());
+                request_id
+            }
+            None => {
+                let generated = request
+                    .extensions()
+                    .get::<tracing_actix_web::RequestId>()
+                    .copied();
+                RequestId(
+                    generated.map_or_else(|| uuid::Uuid::new_v4().to_string(), |id| id.to_string()),
+                )
+            }
+        };
+        request.extensions_mut().insert(request_id);
+        span
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+/// Make the request ID available to the handler's task, and add it to the
+/// response and to the body of server errors.
+///
+/// It must be wrapped by `TracingLogger`, which assigns the ID.
+pub async fn propagate_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse, Error> {
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .cloned()
+        .unwrap_or_else(|| RequestId(uuid::Uuid::new_v4().to_string()));
+    let outcome = CURRENT_REQUEST_ID
+        .scope(request_id.clone(), next.call(req))
+        .await;
+    match outcome {
+        Ok(response) => {
+            let (request, response) = response.map_into_boxed_body().into_parts();
+            let response = with_request_id(response, &request_id).await;
+            Ok(ServiceResponse::new(request, response))
+        }
+        Err(e) => {
+            let response = with_request_id(e.error_response(), &request_id).await;
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
+
+async fn with_request_id(response: HttpResponse, request_id: &RequestId) -> HttpResponse {
+    let mut response = if response.status().is_server_error() {
+        quote_request_id(response, request_id).await
+    } else {
+        response
+    };
+    if let Ok(value) = HeaderValue::from_str(request_id.as_str()) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static("x-request-id"), value);
+    }
+    response
+}
+
+/// Add the request ID to a plain text or JSON error body, for users to quote.
+async fn quote_request_id(response: HttpResponse, request_id: &RequestId) -> HttpResponse {
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let (response, body) = response.into_parts();
+    let Ok(body) = to_bytes(body).await else {
+        return response.set_body(BoxBody::new(()));
+    };
+    let body = if content_type.starts_with("application/json") {
+        match serde_json::from_slice::<serde_json::Value>(&body) {
+            Ok(serde_json::Value::Object(mut fields)) => {
+                fields.insert("request_id".into(), request_id.as_str().into());
+                serde_json::Value::Object(fields).to_string().into()
+            }
+            _ => body,
+        }
+    } else if content_type.starts_with("text/plain") {
+        let mut text = String::from_utf8_lossy(&body).into_owned();
+        text.push_str(&format!("\n\nRequest ID: {}", request_id));
+        text.into()
+    } else {
+        body
+    };
+    response.set_body(BoxBody::new(body))
+}
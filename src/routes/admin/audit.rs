@@ -0,0 +1,179 @@
+//! src/routes/admin/audit.rs
+
+use actix_web::http::header::{ContentDisposition, ContentType};
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Days, NaiveDate};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::audit::{get_audit_events, AuditAction, AuditFilter};
+use crate::utils::e500;
+
+const PAGE_SIZE: i64 = 100;
+const EXPORT_LIMIT: i64 = 10_000;
+
+#[derive(serde::Deserialize)]
+pub struct AuditQuery {
+    action: Option<String>,
+    username: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+}
+
+impl TryFrom<AuditQuery> for AuditFilter {
+    type Error = String;
+
+    /// Empty fields, as submitted by the filter form, mean "no filter".
+    /// `until` is inclusive: events of that whole day are kept.
+    fn try_from(query: AuditQuery) -> Result<Self, Self::Error> {
+        let non_empty = |field: Option<String>| field.filter(|value| !value.trim().is_empty());
+        let parse_date = |field: Option<String>| -> Result<Option<NaiveDate>, String> {
+            non_empty(field)
+                .map(|value| {
+                    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                        .map_err(|_| format!("{} is not a valid YYYY-MM-DD date.", value))
+                })
+                .transpose()
+        };
+
+        let action = non_empty(query.action)
+            .map(|action| AuditAction::try_from(action.as_str()))
+            .transpose()?;
+        let since = parse_date(query.since)?;
+        let until = parse_date(query.until)?;
+        Ok(Self {
+            action,
+            actor_username: non_empty(query.username),
+            since: since.map(|date| date.and_time(chrono::NaiveTime::MIN).and_utc()),
+            until: until
+                .and_then(|date| date.checked_add_days(Days::new(1)))
+                .map(|date| date.and_time(chrono::NaiveTime::MIN).and_utc()),
+        })
+    }
+}
+
+pub async fn audit_log(
+    request: HttpRequest,
+    query: web::Query<AuditQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let filter: AuditFilter = query
+        .into_inner()
+        .try_into()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let events = get_audit_events(&filter, PAGE_SIZE, &pool)
+        .await
+        .map_err(e500)?;
+
+    let mut events_html = String::new();
+    for event in events {
+        writeln!(
+            events_html,
+            r#"        <tr>
+            <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
+            <td><code>{}</code></td>
+        </tr>"#,
+            event.occurred_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            event.action,
+            htmlescape::encode_minimal(event.actor_username.as_deref().unwrap_or("-")),
+            htmlescape::encode_minimal(event.ip_address.as_deref().unwrap_or("-")),
+            htmlescape::encode_minimal(event.user_agent.as_deref().unwrap_or("-")),
+            htmlescape::encode_minimal(&event.details.to_string()),
+        )
+        .unwrap();
+    }
+
+    let selected_action = filter.action.map(|a| a.as_str());
+    let mut actions_html = String::new();
+    for action in AuditAction::ALL {
+        let selected = if selected_action == Some(action.as_str()) {
+            " selected"
+        } else {
+            ""
+        };
+        writeln!(
+            actions_html,
+            r#"                <option value="{0}"{1}>{0}</option>"#,
+            action.as_str(),
+            selected
+        )
+        .unwrap();
+    }
+    let username = htmlescape::encode_attribute(filter.actor_username.as_deref().unwrap_or(""));
+    let since = filter
+        .since
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let until = filter
+        .until
+        .and_then(|d| d.date_naive().checked_sub_days(Days::new(1)))
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let export_url = format!("/admin/audit/export?{}", request.query_string());
+    let export_url = htmlescape::encode_attribute(&export_url);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Audit log</title>
+</head>
+<body>
+    <form action="/admin/audit" method="get">
+        <label>Action
+            <select name="action">
+                <option value="">Any</option>
+{actions_html}
+            </select>
+        </label>
+        <label>Username
+            <input type="text" name="username" value="{username}">
+        </label>
+        <label>From
+            <input type="date" name="since" value="{since}">
+        </label>
+        <label>To
+            <input type="date" name="until" value="{until}">
+        </label>
+        <button type="submit">Filter</button>
+    </form>
+    <p><a href="{export_url}">Export as JSON</a></p>
+    <table>
+        <tr>
+            <th>Time</th>
+            <th>Action</th>
+            <th>User</th>
+            <th>IP address</th>
+            <th>User agent</th>
+            <th>Details</th>
+        </tr>
+{events_html}
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+pub async fn export_audit_log(
+    query: web::Query<AuditQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let filter: AuditFilter = query
+        .into_inner()
+        .try_into()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let events = get_audit_events(&filter, EXPORT_LIMIT, &pool)
+        .await
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .insert_header(ContentDisposition::attachment("audit_events.json"))
+        .json(events))
+}
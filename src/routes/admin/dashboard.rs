@@ -8,12 +8,15 @@ use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::authentication::UserId;
+use crate::session_state::TypedSession;
 use crate::utils::e500;
 
 pub async fn admin_dashboard(
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
+    session: TypedSession,
 ) -> Result<HttpResponse, actix_web::Error> {
+    let csrf_token = session.csrf_token()?;
     let username = get_username(*user_id.into_inner(), &pool)
         .await
         .map_err(e500)?;
@@ -33,8 +36,14 @@ pub async fn admin_dashboard(
     <ol>
         <li><a href="/admin/newsletters">Send a newsletter</a></li>
         <li><a href="/admin/password">Change password</a></li>
+        <li><a href="/admin/tokens">Manage API tokens</a></li>
+        <li><a href="/admin/sessions">Manage active sessions</a></li>
+        <li><a href="/admin/audit">View audit log</a></li>
+        <li><a href="/admin/settings">Reload runtime settings</a></li>
+        <li><a href="/admin/debug/log-level">Raise the log level</a></li>
         <li>
             <form name="logoutForm" action="/admin/logout" method="post">
+                <input type="hidden" name="csrf_token" value="{csrf_token}">
                 <input type="submit" value="Logout"
             </form>
         </li>
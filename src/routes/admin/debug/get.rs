@@ -0,0 +1,79 @@
+//! src/routes/admin/debug/get.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+use super::MAX_RAISE_MINUTES;
+use crate::reload::SettingsReloader;
+use crate::session_state::TypedSession;
+
+pub async fn log_level_page(
+    flash_messages: IncomingFlashMessages,
+    settings_reloader: web::Data<SettingsReloader>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_token = session.csrf_token()?;
+    let log_filter = settings_reloader.log_filter();
+    let directives = log_filter.map_or_else(
+        || "Not managed by the application".to_owned(),
+        |log_filter| log_filter.directives(),
+    );
+    let raised_html = match log_filter.and_then(|log_filter| log_filter.raised()) {
+        Some(raised) => format!(
+            r#"<p>Raised with <code>{}</code> until {}.</p>
+    <form action="/admin/debug/log-level/revert" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <button type="submit">Revert now</button>
+    </form>"#,
+            htmlescape::encode_minimal(&raised.directives),
+            raised.expires_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        ),
+        None => "<p>The log level is not raised.</p>".to_owned(),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Log level</title>
+</head>
+<body>
+    {msg_html}
+    <p>Log filter in use: <code>{directives}</code></p>
+    {raised_html}
+    <form action="/admin/debug/log-level" method="post">
+        <label>Directives
+            <input
+                type="text"
+                placeholder="zero2prod::email_client=debug"
+                name="directives"
+            >
+        </label>
+        <label>For (minutes)
+            <input
+                type="number"
+                min="1"
+                max="{MAX_RAISE_MINUTES}"
+                value="15"
+                name="minutes"
+            >
+        </label>
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <button type="submit">Raise the log level</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+            directives = htmlescape::encode_minimal(&directives),
+        )))
+}
@@ -0,0 +1,10 @@
+//! src/routes/admin/debug/mod.rs
+
+mod get;
+mod post;
+
+pub use get::log_level_page;
+pub use post::{raise_log_level, revert_log_level};
+
+/// How long the log level can be raised for, at most.
+const MAX_RAISE_MINUTES: u64 = 60;
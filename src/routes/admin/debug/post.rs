@@ -0,0 +1,143 @@
+//! src/routes/admin/debug/post.rs
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use super::MAX_RAISE_MINUTES;
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::UserId;
+use crate::reload::SettingsReloader;
+use crate::shutdown::Shutdown;
+use crate::telemetry::{LogFilter, RaisedLogLevel};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    directives: String,
+    minutes: u64,
+}
+
+pub async fn raise_log_level(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    settings_reloader: web::Data<SettingsReloader>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    shutdown: web::Data<Shutdown>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(log_filter) = settings_reloader.log_filter() else {
+        FlashMessage::error("The logs are not managed by the application.").send();
+        return Ok(see_other("/admin/debug/log-level"));
+    };
+    let directives = form.0.directives.trim();
+    if directives.is_empty() {
+        FlashMessage::error("Enter the directives to add, e.g. `zero2prod=debug`.").send();
+        return Ok(see_other("/admin/debug/log-level"));
+    }
+    if !(1..=MAX_RAISE_MINUTES).contains(&form.0.minutes) {
+        FlashMessage::error(format!(
+            "The log level can be raised for 1 to {} minutes.",
+            MAX_RAISE_MINUTES
+        ))
+        .send();
+        return Ok(see_other("/admin/debug/log-level"));
+    }
+
+    let duration = Duration::from_secs(form.0.minutes * 60);
+    let raised = match log_filter.raise(directives, duration) {
+        Ok(raised) => raised,
+        Err(e) => {
+            FlashMessage::error(htmlescape::encode_minimal(&format!("{:#}", e))).send();
+            return Ok(see_other("/admin/debug/log-level"));
+        }
+    };
+    shutdown.tasks().spawn(
+        expire_after(
+            duration,
+            raised.clone(),
+            log_filter.clone(),
+            pool.get_ref().clone(),
+            shutdown.token(),
+        )
+        .in_current_span(),
+    );
+    let event = AuditEvent::new(
+        AuditAction::LogLevelRaised,
+        Some(*user_id.into_inner()),
+        &request,
+    )
+    .with_details(serde_json::json!({
+        "directives": &raised.directives,
+        "expires_at": raised.expires_at,
+    }));
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("The log level has been raised.").send();
+    Ok(see_other("/admin/debug/log-level"))
+}
+
+pub async fn revert_log_level(
+    request: HttpRequest,
+    settings_reloader: web::Data<SettingsReloader>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let reverted = match settings_reloader.log_filter() {
+        Some(log_filter) => log_filter.revert().map_err(e500)?,
+        None => false,
+    };
+    if !reverted {
+        FlashMessage::info("The log level was not raised.").send();
+        return Ok(see_other("/admin/debug/log-level"));
+    }
+
+    let event = AuditEvent::new(
+        AuditAction::LogLevelReverted,
+        Some(*user_id.into_inner()),
+        &request,
+    );
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("The log level has been reverted.").send();
+    Ok(see_other("/admin/debug/log-level"))
+}
+
+/// Revert the raised log level once `duration` has elapsed, unless it has
+/// been replaced or reverted by then.
+///
+/// Shutting down drops it along with the rest of the logging setup.
+async fn expire_after(
+    duration: Duration,
+    raised: RaisedLogLevel,
+    log_filter: LogFilter,
+    pool: PgPool,
+    shutdown: CancellationToken,
+) {
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => {}
+        _ = shutdown.cancelled() => return,
+    }
+    match log_filter.expire(&raised) {
+        Ok(true) => tracing::info!("The raised log level has expired."),
+        Ok(false) => return,
+        Err(e) => {
+            tracing::error!(error.cause_chain = ?e, "Failed to revert the raised log level.");
+            return;
+        }
+    }
+    let event = AuditEvent::without_request(AuditAction::LogLevelReverted, None).with_details(
+        serde_json::json!({
+            "directives": &raised.directives,
+            "expired": true,
+        }),
+    );
+    if let Err(e) = record_audit_event(&pool, event).await {
+        tracing::error!(error.cause_chain = ?e, "Failed to audit an expired log level.");
+    }
+}
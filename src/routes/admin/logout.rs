@@ -1,11 +1,29 @@
 //! src/routes/admin/logout.rs
 
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{revoke_session, UserId};
 use crate::session_state::TypedSession;
-use crate::utils::see_other;
-use actix_web::HttpResponse;
+use crate::utils::{e500, see_other};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
 
-pub async fn log_out(session: TypedSession) -> Result<HttpResponse, actix_web::Error> {
+pub async fn log_out(
+    request: HttpRequest,
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    if let Some(session_id) = session.get_session_id().map_err(e500)? {
+        revoke_session(*user_id, session_id, &pool)
+            .await
+            .map_err(e500)?;
+    }
+    let event = AuditEvent::new(AuditAction::Logout, Some(*user_id), &request);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
     session.log_out();
     FlashMessage::info("You have successfully logged out.").send();
     Ok(see_other("/login"))
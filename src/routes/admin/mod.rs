@@ -1,10 +1,22 @@
 //! src/routes/admin/mod.rs
+mod audit;
 mod dashboard;
+mod debug;
 mod logout;
 mod newsletter;
 mod password;
+mod reauthenticate;
+mod sessions;
+mod settings;
+mod tokens;
 
+pub use audit::{audit_log, export_audit_log};
 pub use dashboard::admin_dashboard;
+pub use debug::*;
 pub use logout::log_out;
 pub use newsletter::*;
 pub use password::*;
+pub use reauthenticate::*;
+pub use sessions::*;
+pub use settings::*;
+pub use tokens::*;
@@ -3,9 +3,14 @@
 use actix_web::http::header::ContentType;
 use actix_web::HttpResponse;
 
-pub async fn newsletter_form() -> Result<HttpResponse, actix_web::Error> {
-    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
-        r#"<!DOCTYPE html>
+use crate::session_state::TypedSession;
+
+pub async fn newsletter_form(session: TypedSession) -> Result<HttpResponse, actix_web::Error> {
+    let csrf_token = session.csrf_token()?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
 <html lang="en">
 <head>
     <meta http-equiv="content-type" content="text/html; charset=utf-8">
@@ -13,6 +18,7 @@ pub async fn newsletter_form() -> Result<HttpResponse, actix_web::Error> {
 </head>
 <body>
     <form action="/admin/newsletters" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
         <label>Title
             <input
                 placeholder="Enter newsletter title"
@@ -31,6 +37,5 @@ pub async fn newsletter_form() -> Result<HttpResponse, actix_web::Error> {
     <p><a href="/admin/dashboard">&lt;- Back</a></p>
 </body>
 </html>"#
-            .to_string(),
-    ))
+        )))
 }
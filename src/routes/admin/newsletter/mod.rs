@@ -4,4 +4,4 @@ mod get;
 mod post;
 
 pub use get::newsletter_form;
-pub use post::publish_newsletter;
+pub use post::{deliver_newsletter, publish_newsletter};
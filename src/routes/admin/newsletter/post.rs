@@ -1,10 +1,12 @@
 //! src/routes/admin/newsletters.rs
 
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use anyhow::Context;
 use sqlx::PgPool;
 
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
 use crate::authentication::UserId;
+use crate::metrics::{metrics, DeliveryOutcome};
 use crate::utils::e500;
 use crate::{domain::SubscriberEmail, email_client::EmailClient};
 
@@ -21,31 +23,66 @@ struct ConfirmedSubscriber {
 
 #[tracing::instrument(
     name = "Publish a newsletter",
-    skip(form, pool, email_client, user_id),
+    skip(request, form, pool, email_client, user_id),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn publish_newsletter(
+    request: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
     user_id: web::ReqData<UserId>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    tracing::Span::current().record("user_id", tracing::field::display(user_id.into_inner()));
+    let user_id = user_id.into_inner();
+    tracing::Span::current().record("user_id", tracing::field::display(user_id));
 
-    let subscribers = get_confirmed_subscribers(&pool).await.map_err(e500)?;
+    deliver_newsletter(
+        &pool,
+        &email_client,
+        &form.title,
+        &form.html_content,
+        &form.text_content,
+    )
+    .await
+    .map_err(e500)?;
+    let event = AuditEvent::new(AuditAction::NewsletterPublished, Some(*user_id), &request)
+        .with_details(serde_json::json!({ "title": &form.title, "channel": "admin" }));
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Send a newsletter issue to every confirmed subscriber.
+#[tracing::instrument(
+    name = "Deliver a newsletter",
+    skip(pool, email_client, html_content, text_content)
+)]
+pub async fn deliver_newsletter(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    title: &str,
+    html_content: &str,
+    text_content: &str,
+) -> Result<(), anyhow::Error> {
+    let started_at = std::time::Instant::now();
+    let subscribers = get_confirmed_subscribers(pool).await?;
     for subscriber in subscribers {
         match subscriber {
-            Ok(subscriber) => email_client
-                .send_email(
-                    &subscriber.email,
-                    &form.title,
-                    &form.html_content,
-                    &form.text_content,
-                )
-                .await
-                .with_context(|| format!("Failed to send newsletter issue to {}", subscriber.email))
-                .map_err(e500)?,
+            Ok(subscriber) => {
+                let outcome = email_client
+                    .send_email(&subscriber.email, title, html_content, text_content)
+                    .await;
+                metrics().record_newsletter_delivery(match outcome {
+                    Ok(()) => DeliveryOutcome::Sent,
+                    Err(_) => DeliveryOutcome::Failed,
+                });
+                outcome.with_context(|| {
+                    format!("Failed to send newsletter issue to {}", subscriber.email)
+                })?
+            }
             Err(error) => {
+                metrics().record_newsletter_delivery(DeliveryOutcome::Skipped);
                 tracing::warn!(
                 error.cause_chain = ?error,
                 "Skipping a confirmed subscriber. \
@@ -54,7 +91,8 @@ pub async fn publish_newsletter(
             }
         }
     }
-    Ok(HttpResponse::Ok().finish())
+    metrics().observe_newsletter_delivery_duration(started_at.elapsed());
+    Ok(())
 }
 
 #[tracing::instrument(name = "Get confirmed subscribers", skip(pool))]
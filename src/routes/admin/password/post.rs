@@ -1,9 +1,17 @@
 //! src/routes/admin/password/post.rs
 
-use crate::authentication::{validate_credentials, AuthError, Credentials, UserId};
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{
+    check_password_policy, revoke_other_sessions, send_security_notification, validate_credentials,
+    AuthError, Credentials, PasswordHashing, SecurityNotification, SessionMetadata, UserId,
+};
+use crate::email_client::EmailClient;
 use crate::routes::admin::dashboard::get_username;
+use crate::session_state::TypedSession;
+use crate::startup::ApplicationBaseUrl;
+use crate::telemetry::spawn_blocking_with_tracing;
 use crate::utils::{e500, see_other};
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 
@@ -16,10 +24,16 @@ pub struct FormData {
     new_password_check: Secret<String>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn change_password(
+    request: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
     user_id: web::ReqData<UserId>,
+    session: TypedSession,
 ) -> Result<HttpResponse, actix_web::Error> {
     if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
         FlashMessage::error(
@@ -29,23 +43,24 @@ pub async fn change_password(
         return Ok(see_other("/admin/password"));
     }
 
-    let new_password_length = form.new_password.expose_secret().len();
-    if !(12..=129).contains(&new_password_length) {
-        FlashMessage::error(
-            "Invalid password length - the new password must be between 12 and 129 characters.",
-        )
-        .send();
-        return Ok(see_other("/admin/password"));
-    }
-
     let user_id = user_id.into_inner();
     let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    let new_password = form.new_password.clone();
+    let policy_username = username.clone();
+    let policy_check =
+        spawn_blocking_with_tracing(move || check_password_policy(&new_password, &policy_username))
+            .await
+            .map_err(e500)?;
+    if let Err(rejection) = policy_check {
+        FlashMessage::error(rejection.to_string()).send();
+        return Ok(see_other("/admin/password"));
+    }
 
     let credentials = Credentials {
         username,
         password: form.0.current_password,
     };
-    if let Err(e) = validate_credentials(credentials, &pool).await {
+    if let Err(e) = validate_credentials(credentials, &pool, &hashing).await {
         return match e {
             AuthError::InvalidCredentials(_) => {
                 FlashMessage::error("The current password is incorrect.").send();
@@ -54,9 +69,31 @@ pub async fn change_password(
             AuthError::UnexpectedError(_) => Err(e500(e)),
         };
     }
-    crate::authentication::change_password(*user_id, form.0.new_password, &pool)
+    crate::authentication::change_password(*user_id, form.0.new_password, &pool, &hashing)
+        .await
+        .map_err(e500)?;
+    // Whoever else knew the old password must not keep a foothold through a session.
+    if let Some(session_id) = session.get_session_id().map_err(e500)? {
+        revoke_other_sessions(*user_id, session_id, &pool)
+            .await
+            .map_err(e500)?;
+    }
+    let event = AuditEvent::new(AuditAction::PasswordChanged, Some(*user_id), &request);
+    record_audit_event(pool.get_ref(), event)
         .await
         .map_err(e500)?;
+    if let Err(e) = send_security_notification(
+        SecurityNotification::PasswordChanged,
+        *user_id,
+        &SessionMetadata::from_request(&request),
+        &pool,
+        &email_client,
+        &base_url.0,
+    )
+    .await
+    {
+        tracing::warn!(error.cause_chain = ?e, "Failed to notify a password change.");
+    }
     FlashMessage::error("Your password has been changed.").send();
     Ok(see_other("/admin/password"))
 }
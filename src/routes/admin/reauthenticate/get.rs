@@ -0,0 +1,51 @@
+//! src/routes/admin/reauthenticate/get.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+use super::{return_location, ReauthenticateQuery};
+use crate::session_state::TypedSession;
+
+pub async fn reauthenticate_form(
+    query: web::Query<ReauthenticateQuery>,
+    flash_messages: IncomingFlashMessages,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let csrf_token = session.csrf_token()?;
+    let next = htmlescape::encode_attribute(return_location(query.next.as_deref()));
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Confirm your password</title>
+</head>
+<body>
+    {msg_html}
+    <p>This action is sensitive: confirm your password to go ahead.</p>
+    <form action="/admin/reauthenticate" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <input type="hidden" name="next" value="{next}">
+        <label>Password
+            <input
+                type="password"
+                placeholder="Enter your password"
+                name="password"
+            >
+        </label>
+        <br>
+        <button type="submit">Confirm</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,23 @@
+//! src/routes/admin/reauthenticate/mod.rs
+
+mod get;
+mod post;
+
+pub use get::reauthenticate_form;
+pub use post::reauthenticate;
+
+/// Where to send the user once they have entered their password.
+///
+/// Only pages of the admin area qualify, so that the parameter cannot be used
+/// to bounce users to another site.
+fn return_location(next: Option<&str>) -> &str {
+    match next {
+        Some(next) if next.starts_with("/admin/") && !next.contains(['\\', '\r', '\n']) => next,
+        _ => "/admin/dashboard",
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ReauthenticateQuery {
+    next: Option<String>,
+}
@@ -0,0 +1,63 @@
+//! src/routes/admin/reauthenticate/post.rs
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use chrono::Utc;
+use secrecy::Secret;
+use sqlx::PgPool;
+
+use super::return_location;
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{
+    validate_credentials, AuthError, Credentials, PasswordHashing, UserId,
+};
+use crate::routes::admin::dashboard::get_username;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    password: Secret<String>,
+    next: Option<String>,
+}
+
+#[tracing::instrument(skip(request, form, pool, hashing, session), fields(user_id = %*user_id))]
+pub async fn reauthenticate(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let next = return_location(form.next.as_deref()).to_owned();
+    let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    let credentials = Credentials {
+        username: username.clone(),
+        password: form.0.password,
+    };
+    match validate_credentials(credentials, &pool, &hashing).await {
+        Ok(_) => {
+            session.insert_authenticated_at(Utc::now()).map_err(e500)?;
+            Ok(see_other(&next))
+        }
+        Err(AuthError::InvalidCredentials(_)) => {
+            let event = AuditEvent::new(AuditAction::LoginFailed, Some(*user_id), &request)
+                .with_details(serde_json::json!({
+                    "username": username,
+                    "method": "reauthentication",
+                }));
+            record_audit_event(pool.get_ref(), event)
+                .await
+                .map_err(e500)?;
+            FlashMessage::error("The password is incorrect.").send();
+            let retry_location = format!(
+                "/admin/reauthenticate?next={}",
+                url::form_urlencoded::byte_serialize(next.as_bytes()).collect::<String>()
+            );
+            Ok(see_other(&retry_location))
+        }
+        Err(e) => Err(e500(e)),
+    }
+}
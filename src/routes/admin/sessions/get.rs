@@ -0,0 +1,89 @@
+//! src/routes/admin/sessions/get.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{get_active_sessions, UserId};
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+
+pub async fn active_sessions_page(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_token = session.csrf_token()?;
+    let current_session_id = session.get_session_id().map_err(e500)?;
+    let sessions = get_active_sessions(*user_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?;
+    let mut sessions_html = String::new();
+    for active_session in sessions {
+        let action = if Some(active_session.session_id) == current_session_id {
+            "This session".to_string()
+        } else {
+            format!(
+                r#"<form action="/admin/sessions/{}/revoke" method="post">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    <button type="submit">Revoke</button>
+                </form>"#,
+                active_session.session_id, csrf_token
+            )
+        };
+        writeln!(
+            sessions_html,
+            r#"        <tr>
+            <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
+        </tr>"#,
+            htmlescape::encode_minimal(active_session.user_agent.as_deref().unwrap_or("Unknown")),
+            htmlescape::encode_minimal(active_session.ip_address.as_deref().unwrap_or("Unknown")),
+            active_session.created_at.format("%Y-%m-%d %H:%M UTC"),
+            active_session.last_seen_at.format("%Y-%m-%d %H:%M UTC"),
+            action,
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Active sessions</title>
+</head>
+<body>
+    {msg_html}
+    <table>
+        <tr>
+            <th>Device</th>
+            <th>IP address</th>
+            <th>Signed in</th>
+            <th>Last seen</th>
+            <th></th>
+        </tr>
+{sessions_html}
+    </table>
+    <form action="/admin/sessions/revoke-others" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <button type="submit">Log out all other sessions</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,7 @@
+//! src/routes/admin/sessions/mod.rs
+
+mod get;
+mod post;
+
+pub use get::active_sessions_page;
+pub use post::{revoke_other_sessions, revoke_session};
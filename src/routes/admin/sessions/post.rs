@@ -0,0 +1,63 @@
+//! src/routes/admin/sessions/post.rs
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::UserId;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+pub async fn revoke_session(
+    request: HttpRequest,
+    session_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let session_id = session_id.into_inner();
+    if session.get_session_id().map_err(e500)? == Some(session_id) {
+        FlashMessage::error("Use the logout button to end the current session.").send();
+        return Ok(see_other("/admin/sessions"));
+    }
+
+    let user_id = user_id.into_inner();
+    let revoked = crate::authentication::revoke_session(*user_id, session_id, &pool)
+        .await
+        .map_err(e500)?;
+    if revoked {
+        let event = AuditEvent::new(AuditAction::SessionRevoked, Some(*user_id), &request)
+            .with_details(serde_json::json!({ "session_id": session_id }));
+        record_audit_event(pool.get_ref(), event)
+            .await
+            .map_err(e500)?;
+        FlashMessage::info("The session has been revoked.").send();
+    } else {
+        FlashMessage::error("No such session.").send();
+    }
+    Ok(see_other("/admin/sessions"))
+}
+
+pub async fn revoke_other_sessions(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(session_id) = session.get_session_id().map_err(e500)? else {
+        return Ok(see_other("/login"));
+    };
+    let user_id = user_id.into_inner();
+    let revoked = crate::authentication::revoke_other_sessions(*user_id, session_id, &pool)
+        .await
+        .map_err(e500)?;
+    let event = AuditEvent::new(AuditAction::SessionRevoked, Some(*user_id), &request)
+        .with_details(serde_json::json!({ "kept_session_id": session_id, "revoked": revoked }));
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info(format!("{} other session(s) have been revoked.", revoked)).send();
+    Ok(see_other("/admin/sessions"))
+}
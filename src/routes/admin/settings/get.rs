@@ -0,0 +1,64 @@
+//! src/routes/admin/settings/get.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+use crate::reload::SettingsReloader;
+use crate::session_state::TypedSession;
+
+pub async fn runtime_settings_page(
+    flash_messages: IncomingFlashMessages,
+    settings_reloader: web::Data<SettingsReloader>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_token = session.csrf_token()?;
+    let mut settings_html = String::new();
+    for (key, value) in settings_reloader.reloadable_values() {
+        writeln!(
+            settings_html,
+            r#"        <tr>
+            <td>{}</td>
+            <td>{}</td>
+        </tr>"#,
+            key,
+            htmlescape::encode_minimal(&value),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Runtime settings</title>
+</head>
+<body>
+    {msg_html}
+    <p>These settings are read again from the configuration on reload or
+    on SIGHUP. Any other change needs a restart.</p>
+    <table>
+        <tr>
+            <th>Setting</th>
+            <th>Value</th>
+        </tr>
+{settings_html}
+    </table>
+    <form action="/admin/settings/reload" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <button type="submit">Reload the configuration</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,7 @@
+//! src/routes/admin/settings/mod.rs
+
+mod get;
+mod post;
+
+pub use get::runtime_settings_page;
+pub use post::reload_settings;
@@ -0,0 +1,53 @@
+//! src/routes/admin/settings/post.rs
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::UserId;
+use crate::reload::SettingsReloader;
+use crate::utils::{e500, see_other};
+
+pub async fn reload_settings(
+    request: HttpRequest,
+    settings_reloader: web::Data<SettingsReloader>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let report = match settings_reloader.reload() {
+        Ok(report) => report,
+        Err(e) => {
+            FlashMessage::error(format!(
+                "The configuration was not reloaded. {}",
+                htmlescape::encode_minimal(&format!("{:#}", e))
+            ))
+            .send();
+            return Ok(see_other("/admin/settings"));
+        }
+    };
+
+    let event = AuditEvent::new(
+        AuditAction::SettingsReloaded,
+        Some(*user_id.into_inner()),
+        &request,
+    )
+    .with_details(report.audit_details());
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    if report.applied.is_empty() && report.requires_restart.is_empty() {
+        FlashMessage::info("The configuration has not changed.").send();
+    }
+    if !report.applied.is_empty() {
+        FlashMessage::info(format!("Applied: {}.", report.applied.join(", "))).send();
+    }
+    if !report.requires_restart.is_empty() {
+        FlashMessage::warning(format!(
+            "Restart the application to apply: {}.",
+            report.requires_restart.join(", ")
+        ))
+        .send();
+    }
+    Ok(see_other("/admin/settings"))
+}
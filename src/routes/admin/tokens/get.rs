@@ -0,0 +1,108 @@
+//! src/routes/admin/tokens/get.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{get_active_api_tokens, ApiScope, UserId};
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+
+pub async fn api_tokens_page(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let csrf_token = session.csrf_token()?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let tokens = get_active_api_tokens(*user_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?;
+    let mut tokens_html = String::new();
+    for token in tokens {
+        writeln!(
+            tokens_html,
+            r#"        <tr>
+            <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
+            <td>
+                <form action="/admin/tokens/{}/revoke" method="post">
+                    <input type="hidden" name="csrf_token" value="{}">
+                    <button type="submit">Revoke</button>
+                </form>
+            </td>
+        </tr>"#,
+            htmlescape::encode_minimal(&token.name),
+            token.scopes.join(", "),
+            token.created_at.format("%Y-%m-%d %H:%M UTC"),
+            token.expires_at.format("%Y-%m-%d %H:%M UTC"),
+            token.token_id,
+            csrf_token,
+        )
+        .unwrap();
+    }
+
+    let mut scopes_html = String::new();
+    for scope in ApiScope::ALL {
+        writeln!(
+            scopes_html,
+            r#"        <label><input type="checkbox" name="scopes" value="{0}"> {0}</label><br>"#,
+            scope.as_str()
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>API tokens</title>
+</head>
+<body>
+    {msg_html}
+    <table>
+        <tr>
+            <th>Name</th>
+            <th>Scopes</th>
+            <th>Created</th>
+            <th>Expires</th>
+            <th></th>
+        </tr>
+{tokens_html}
+    </table>
+    <h2>Create a new token</h2>
+    <form action="/admin/tokens" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <label>Name
+            <input type="text" placeholder="Enter a name for the token" name="name">
+        </label>
+        <br>
+{scopes_html}
+        <label>Expires in
+            <select name="expires_in_days">
+                <option value="7">7 days</option>
+                <option value="30" selected>30 days</option>
+                <option value="90">90 days</option>
+                <option value="365">365 days</option>
+            </select>
+        </label>
+        <br>
+        <button type="submit">Create token</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
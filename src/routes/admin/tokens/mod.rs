@@ -0,0 +1,7 @@
+//! src/routes/admin/tokens/mod.rs
+
+mod get;
+mod post;
+
+pub use get::api_tokens_page;
+pub use post::{create_api_token, revoke_api_token};
@@ -0,0 +1,123 @@
+//! src/routes/admin/tokens/post.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use chrono::{Duration, Utc};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{ApiScope, NewApiToken, UserId};
+use crate::utils::{e500, see_other};
+
+/// The creation form is read as raw pairs because `scopes` can be repeated.
+fn parse_new_token(form: Vec<(String, String)>) -> Result<NewApiToken, String> {
+    let mut name = None;
+    let mut scopes = Vec::new();
+    let mut expires_in_days = None;
+    for (key, value) in form {
+        match key.as_str() {
+            "name" => name = Some(value.trim().to_owned()),
+            "scopes" => {
+                let scope = ApiScope::try_from(value.as_str())?;
+                if !scopes.contains(&scope) {
+                    scopes.push(scope);
+                }
+            }
+            "expires_in_days" => expires_in_days = value.parse::<i64>().ok(),
+            _ => {}
+        }
+    }
+
+    let name = name
+        .filter(|name| !name.is_empty() && name.chars().count() <= 100)
+        .ok_or("The token name must be between 1 and 100 characters.")?;
+    if scopes.is_empty() {
+        return Err("Select at least one scope for the token.".into());
+    }
+    let expires_in_days = expires_in_days
+        .filter(|days| (1..=365).contains(days))
+        .ok_or("The token must expire within 1 to 365 days.")?;
+
+    Ok(NewApiToken {
+        name,
+        scopes,
+        expires_at: Utc::now() + Duration::days(expires_in_days),
+    })
+}
+
+pub async fn create_api_token(
+    request: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let new_token = match parse_new_token(form.into_inner()) {
+        Ok(new_token) => new_token,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/tokens"));
+        }
+    };
+    let user_id = user_id.into_inner();
+    let audit_details = serde_json::json!({
+        "name": &new_token.name,
+        "scopes": new_token.scopes.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+        "expires_at": new_token.expires_at,
+    });
+    let name = htmlescape::encode_minimal(&new_token.name);
+    let token = crate::authentication::create_api_token(*user_id, new_token, &pool)
+        .await
+        .map_err(e500)?;
+    let event = AuditEvent::new(AuditAction::ApiTokenCreated, Some(*user_id), &request)
+        .with_details(audit_details);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+
+    // The token is never stored in plain text: this page is the only chance to copy it.
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>API token created</title>
+</head>
+<body>
+    <p>Your new API token <b>{name}</b>:</p>
+    <p><code id="api-token">{}</code></p>
+    <p>Copy it now - it will not be shown again.</p>
+    <p><a href="/admin/tokens">&lt;- Back</a></p>
+</body>
+</html>"#,
+            token.expose_secret()
+        )))
+}
+
+pub async fn revoke_api_token(
+    request: HttpRequest,
+    token_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let token_id = token_id.into_inner();
+    let revoked = crate::authentication::revoke_api_token(*user_id, token_id, &pool)
+        .await
+        .map_err(e500)?;
+    if revoked {
+        let event = AuditEvent::new(AuditAction::ApiTokenRevoked, Some(*user_id), &request)
+            .with_details(serde_json::json!({ "token_id": token_id }));
+        record_audit_event(pool.get_ref(), event)
+            .await
+            .map_err(e500)?;
+        FlashMessage::info("The API token has been revoked.").send();
+    } else {
+        FlashMessage::error("No such API token.").send();
+    }
+    Ok(see_other("/admin/tokens"))
+}
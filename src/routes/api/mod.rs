@@ -0,0 +1,49 @@
+//! src/routes/api/mod.rs
+
+mod newsletters;
+mod subscribers;
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+
+use crate::authentication::{ApiScope, ApiScopes};
+use crate::utils::error_chain_fmt;
+
+pub use newsletters::api_publish_newsletter;
+pub use subscribers::api_list_subscribers;
+
+#[derive(thiserror::Error)]
+pub enum ApiError {
+    #[error("This API token lacks the `{}` scope.", .0.as_str())]
+    MissingScope(ApiScope),
+    #[error("Something went wrong.")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::MissingScope(_) => StatusCode::FORBIDDEN,
+            ApiError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .json(serde_json::json!({ "error": self.to_string() }))
+    }
+}
+
+fn require_scope(scopes: &ApiScopes, scope: ApiScope) -> Result<(), ApiError> {
+    if scopes.contains(scope) {
+        Ok(())
+    } else {
+        Err(ApiError::MissingScope(scope))
+    }
+}
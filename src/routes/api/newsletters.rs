@@ -0,0 +1,45 @@
+//! src/routes/api/newsletters.rs
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+use super::{require_scope, ApiError};
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{ApiScope, ApiScopes, UserId};
+use crate::email_client::EmailClient;
+use crate::routes::deliver_newsletter;
+
+#[derive(serde::Deserialize)]
+pub struct NewsletterIssue {
+    title: String,
+    text_content: String,
+    html_content: String,
+}
+
+#[tracing::instrument(
+    name = "Publish a newsletter through the API",
+    skip(request, body, pool, email_client, user_id, scopes),
+    fields(user_id = %*user_id)
+)]
+pub async fn api_publish_newsletter(
+    request: HttpRequest,
+    body: web::Json<NewsletterIssue>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    user_id: web::ReqData<UserId>,
+    scopes: web::ReqData<ApiScopes>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&scopes, ApiScope::PublishNewsletters)?;
+    deliver_newsletter(
+        &pool,
+        &email_client,
+        &body.title,
+        &body.html_content,
+        &body.text_content,
+    )
+    .await?;
+    let event = AuditEvent::new(AuditAction::NewsletterPublished, Some(**user_id), &request)
+        .with_details(serde_json::json!({ "title": &body.title, "channel": "api" }));
+    record_audit_event(pool.get_ref(), event).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "published" })))
+}
@@ -0,0 +1,37 @@
+//! src/routes/api/subscribers.rs
+
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::{require_scope, ApiError};
+use crate::authentication::{ApiScope, ApiScopes};
+
+#[derive(serde::Serialize)]
+struct Subscriber {
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "List subscribers through the API", skip(pool, scopes))]
+pub async fn api_list_subscribers(
+    pool: web::Data<PgPool>,
+    scopes: web::ReqData<ApiScopes>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&scopes, ApiScope::ReadSubscribers)?;
+    let subscribers = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT email, name, status, subscribed_at
+        FROM subscriptions
+        ORDER BY subscribed_at
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the subscribers.")?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "subscribers": subscribers })))
+}
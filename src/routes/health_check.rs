@@ -1,7 +1,30 @@
 //! src/routes/health_check.rs
 
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::email_client::EmailClient;
+use crate::health::ReadinessChecks;
 
 pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().finish()
 }
+
+/// The process is up and serving requests, whatever the state of its dependencies.
+pub async fn health_live() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "up" }))
+}
+
+/// The process can serve requests, as every dependency it needs answers.
+pub async fn health_ready(
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    readiness_checks: web::Data<ReadinessChecks>,
+) -> HttpResponse {
+    let report = readiness_checks.check(&pool, &email_client).await;
+    if report.is_ready() {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
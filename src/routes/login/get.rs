@@ -1,17 +1,30 @@
 //! src/routes/login/get.rs
 
 use actix_web::http::header::ContentType;
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use std::fmt::Write;
 
-pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+use crate::authentication::OidcClient;
+use crate::session_state::TypedSession;
+
+pub async fn login_form(
+    flash_messages: IncomingFlashMessages,
+    session: TypedSession,
+    oidc_client: Option<web::Data<OidcClient>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let csrf_token = session.csrf_token()?;
     let mut flash_message_html = String::new();
     for m in flash_messages.iter() {
         writeln!(flash_message_html, "<p><i>{}</i></p>", m.content()).unwrap();
     }
+    let sso_html = if oidc_client.is_some() {
+        r#"    <p><a href="/login/oidc">Sign in with single sign-on</a></p>"#
+    } else {
+        ""
+    };
 
-    HttpResponse::Ok()
+    Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
             r#"<!DOCTYPE html>
@@ -25,16 +38,21 @@ pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
 <body>
 {flash_message_html}
     <form action="/login" , method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
         <label>Username
             <input type="text" placeholder="Enter Username" name="username">
         </label>
         <label>Password
             <input type="password" placeholder="Enter Password" name="password">
         </label>
+        <label>
+            <input type="checkbox" name="remember_me" value="on"> Remember me
+        </label>
         <button type="submit">Login</button>
     </form>
+{sso_html}
 </body>
 
 </html>"#
-        ))
+        )))
 }
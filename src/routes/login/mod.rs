@@ -1,7 +1,9 @@
 //! src/routes/login/mod.rs
 
 mod get;
+mod oidc;
 mod post;
 
 pub use get::login_form;
+pub use oidc::{oidc_callback, oidc_login};
 pub use post::login;
@@ -0,0 +1,145 @@
+//! src/routes/login/oidc.rs
+
+use actix_web::error::InternalError;
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::post::{login_redirect, start_admin_session, LoginError};
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{find_oidc_user, AuthError, OidcClient};
+use crate::configuration::SessionSettings;
+use crate::email_client::EmailClient;
+use crate::session_state::TypedSession;
+use crate::shutdown::Shutdown;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::see_other;
+
+#[derive(serde::Deserialize)]
+pub struct CallbackParameters {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+#[tracing::instrument(skip(oidc_client, session))]
+pub async fn oidc_login(
+    oidc_client: web::Data<OidcClient>,
+    session: TypedSession,
+) -> Result<HttpResponse, InternalError<LoginError>> {
+    let (authorization_url, pending) = oidc_client
+        .authorization_request()
+        .await
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+    session
+        .insert_pending_oidc_login(&pending)
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+    Ok(see_other(authorization_url.as_str()))
+}
+
+#[tracing::instrument(
+    skip(
+        request,
+        parameters,
+        oidc_client,
+        pool,
+        session_settings,
+        email_client,
+        base_url,
+        session,
+        shutdown
+    ),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn oidc_callback(
+    request: HttpRequest,
+    parameters: web::Query<CallbackParameters>,
+    oidc_client: web::Data<OidcClient>,
+    pool: web::Data<PgPool>,
+    session_settings: web::Data<SessionSettings>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    session: TypedSession,
+    shutdown: web::Data<Shutdown>,
+) -> Result<HttpResponse, InternalError<LoginError>> {
+    match authenticate(parameters.0, &oidc_client, &pool, &session).await {
+        Ok((user_id, username)) => {
+            tracing::Span::current()
+                .record("username", tracing::field::display(&username))
+                .record("user_id", tracing::field::display(&user_id));
+            let audit_details = serde_json::json!({ "username": username, "method": "oidc" });
+            // Single sign-on sessions are never remembered: the identity
+            // provider keeps its own session to sign the user back in with.
+            start_admin_session(
+                user_id,
+                false,
+                audit_details,
+                &request,
+                &session,
+                &session_settings,
+                &pool,
+                &email_client,
+                &base_url.0,
+                &shutdown,
+            )
+            .await
+            .map_err(login_redirect)
+        }
+        Err(e) => {
+            let e = match e {
+                AuthError::InvalidCredentials(_) => {
+                    let event = AuditEvent::new(AuditAction::LoginFailed, None, &request)
+                        .with_details(serde_json::json!({ "method": "oidc" }));
+                    record_audit_event(pool.get_ref(), event)
+                        .await
+                        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+                    LoginError::AuthError(e.into())
+                }
+                AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
+            };
+            Err(login_redirect(e))
+        }
+    }
+}
+
+async fn authenticate(
+    parameters: CallbackParameters,
+    oidc_client: &OidcClient,
+    pool: &PgPool,
+    session: &TypedSession,
+) -> Result<(Uuid, String), AuthError> {
+    let pending = session.take_pending_oidc_login();
+    let claims = match (pending, parameters) {
+        (
+            _,
+            CallbackParameters {
+                error: Some(error), ..
+            },
+        ) => {
+            return Err(AuthError::InvalidCredentials(anyhow::anyhow!(
+                "The identity provider returned an error: {}",
+                error
+            )))
+        }
+        (
+            Some(pending),
+            CallbackParameters {
+                code: Some(code),
+                state: Some(state),
+                ..
+            },
+        ) => oidc_client.exchange_code(pending, &state, &code).await?,
+        _ => {
+            return Err(AuthError::InvalidCredentials(anyhow::anyhow!(
+                "No sign-in with the identity provider is in progress."
+            )))
+        }
+    };
+    find_oidc_user(&claims, pool).await?.ok_or_else(|| {
+        AuthError::InvalidCredentials(anyhow::anyhow!(
+            "No user is linked to the identity provider account {}.",
+            claims.sub
+        ))
+    })
+}
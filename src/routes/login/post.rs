@@ -4,20 +4,34 @@ use actix_web::error::InternalError;
 use actix_web::http::header::LOCATION;
 use actix_web::http::StatusCode;
 use actix_web::web;
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
 use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::Utc;
 use secrecy::Secret;
 use sqlx::PgPool;
+use tracing::Instrument;
+use uuid::Uuid;
 
-use crate::authentication::validate_credentials;
-use crate::authentication::{AuthError, Credentials};
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{
+    record_session, remember_device, send_security_notification, validate_credentials,
+};
+use crate::authentication::{
+    AuthError, Credentials, PasswordHashing, SecurityNotification, SessionMetadata,
+};
+use crate::configuration::SessionSettings;
+use crate::email_client::EmailClient;
 use crate::session_state::TypedSession;
-use crate::utils::error_chain_fmt;
+use crate::shutdown::Shutdown;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{error_chain_fmt, see_other};
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
     username: String,
     password: Secret<String>,
+    remember_me: Option<String>,
 }
 
 #[derive(thiserror::Error)]
@@ -44,33 +58,66 @@ impl ResponseError for LoginError {
 }
 
 #[tracing::instrument(
-    skip(form, pool, session),
+    skip(
+        request,
+        form,
+        pool,
+        hashing,
+        session_settings,
+        email_client,
+        base_url,
+        session,
+        shutdown
+    ),
     fields(username=tracing::field::Empty, user_id = tracing::field::Empty)
 )]
+#[allow(clippy::too_many_arguments)]
 pub async fn login(
+    request: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    session_settings: web::Data<SessionSettings>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
     session: TypedSession,
+    shutdown: web::Data<Shutdown>,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
+    let remember_me = form.remember_me.is_some();
     let credentials = Credentials {
         username: form.0.username,
         password: form.0.password,
     };
     tracing::Span::current().record("username", tracing::field::display(&credentials.username));
-    match validate_credentials(credentials, &pool).await {
+    let audit_details = serde_json::json!({ "username": &credentials.username });
+    match validate_credentials(credentials, &pool, &hashing).await {
         Ok(user_id) => {
             tracing::Span::current().record("user_id", tracing::field::display(&user_id));
-            session.renew();
-            session
-                .insert_user_id(user_id)
-                .map_err(|e| login_redirect(LoginError::AuthError(e.into())))?;
-            Ok(HttpResponse::SeeOther()
-                .insert_header((LOCATION, "/admin/dashboard"))
-                .finish())
+            start_admin_session(
+                user_id,
+                remember_me,
+                audit_details,
+                &request,
+                &session,
+                &session_settings,
+                &pool,
+                &email_client,
+                &base_url.0,
+                &shutdown,
+            )
+            .await
+            .map_err(login_redirect)
         }
         Err(e) => {
             let e = match e {
-                AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
+                AuthError::InvalidCredentials(_) => {
+                    let event = AuditEvent::new(AuditAction::LoginFailed, None, &request)
+                        .with_details(audit_details);
+                    record_audit_event(pool.get_ref(), event)
+                        .await
+                        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+                    LoginError::AuthError(e.into())
+                }
                 AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
             };
             Err(login_redirect(e))
@@ -78,7 +125,82 @@ pub async fn login(
     }
 }
 
-fn login_redirect(e: LoginError) -> InternalError<LoginError> {
+/// Open an authenticated session for `user_id` and send them to the dashboard.
+///
+/// Users are notified by email when they log in from a device they haven't used before.
+/// The email is sent in the background: the login neither waits for the email
+/// provider nor fails because it is unavailable.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn start_admin_session(
+    user_id: Uuid,
+    remember_me: bool,
+    audit_details: serde_json::Value,
+    request: &HttpRequest,
+    session: &TypedSession,
+    session_settings: &SessionSettings,
+    pool: &PgPool,
+    email_client: &web::Data<EmailClient>,
+    base_url: &str,
+    shutdown: &Shutdown,
+) -> Result<HttpResponse, LoginError> {
+    let lifetime = session_settings.lifetime(remember_me);
+    session.renew();
+    let session_id = Uuid::new_v4();
+    session
+        .insert_user_id(user_id)
+        .map_err(|e| LoginError::AuthError(e.into()))?;
+    session
+        .insert_session_id(session_id)
+        .map_err(|e| LoginError::AuthError(e.into()))?;
+    let now = Utc::now();
+    session
+        .insert_authenticated_at(now)
+        .map_err(|e| LoginError::AuthError(e.into()))?;
+    let expires_at = now
+        + chrono::Duration::from_std(lifetime.absolute_timeout)
+            .context("The session lifetime is out of range.")?;
+    session
+        .insert_expires_at(expires_at)
+        .map_err(|e| LoginError::AuthError(e.into()))?;
+    session
+        .insert_remember_me(remember_me)
+        .map_err(|e| LoginError::AuthError(e.into()))?;
+    let metadata = SessionMetadata::from_request(request);
+    let is_new_device = remember_device(user_id, &metadata, pool).await?;
+    if is_new_device {
+        let metadata = metadata.clone();
+        let pool = pool.clone();
+        let email_client = email_client.clone();
+        let base_url = base_url.to_owned();
+        shutdown.tasks().spawn(
+            async move {
+                if let Err(e) = send_security_notification(
+                    SecurityNotification::NewDeviceLogin,
+                    user_id,
+                    &metadata,
+                    &pool,
+                    &email_client,
+                    &base_url,
+                )
+                .await
+                {
+                    tracing::warn!(
+                        error.cause_chain = ?e,
+                        "Failed to notify a login from a new device."
+                    );
+                }
+            }
+            .in_current_span(),
+        );
+    }
+    record_session(session_id, user_id, metadata, lifetime, pool).await?;
+    let event = AuditEvent::new(AuditAction::LoginSucceeded, Some(user_id), request)
+        .with_details(audit_details);
+    record_audit_event(pool, event).await?;
+    Ok(see_other("/admin/dashboard"))
+}
+
+pub(super) fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
     let response = HttpResponse::SeeOther()
         .insert_header((LOCATION, "/login"))
@@ -1,15 +1,23 @@
 //! src/routes/mod.rs
 
 mod admin;
+mod api;
 mod health_check;
 mod home;
 mod login;
+mod revoke_sessions;
+mod setup;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_form;
 
 pub use admin::*;
+pub use api::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
+pub use revoke_sessions::*;
+pub use setup::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_form::*;
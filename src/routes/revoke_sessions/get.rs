@@ -0,0 +1,55 @@
+//! src/routes/revoke_sessions/get.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use super::INVALID_LINK_MESSAGE;
+use crate::authentication::is_valid_session_revocation_token;
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    token: String,
+}
+
+/// The page security notifications link to.
+///
+/// Revoking takes a click on a button: mail scanners that follow links must
+/// not log users out.
+pub async fn revoke_sessions_form(
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = query.0.token;
+    if !is_valid_session_revocation_token(&token, &pool)
+        .await
+        .map_err(e500)?
+    {
+        return Ok(HttpResponse::NotFound().body(INVALID_LINK_MESSAGE));
+    }
+    let csrf_token = session.csrf_token()?;
+    let token = htmlescape::encode_attribute(&token);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Log out everywhere</title>
+</head>
+<body>
+    <p>If you don't recognise the activity you were notified about, log out of
+    every session of your account, then log in again and change your password.</p>
+    <form action="/account/revoke-sessions" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <input type="hidden" name="token" value="{token}">
+        <button type="submit">Log out everywhere</button>
+    </form>
+</body>
+</html>"#,
+        )))
+}
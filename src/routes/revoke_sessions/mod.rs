@@ -0,0 +1,9 @@
+//! src/routes/revoke_sessions/mod.rs
+
+mod get;
+mod post;
+
+pub use get::revoke_sessions_form;
+pub use post::revoke_sessions;
+
+const INVALID_LINK_MESSAGE: &str = "This link is invalid, has expired or has already been used.";
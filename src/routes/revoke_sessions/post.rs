@@ -0,0 +1,56 @@
+//! src/routes/revoke_sessions/post.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+use super::INVALID_LINK_MESSAGE;
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::revoke_sessions_with_token;
+use crate::utils::e500;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    token: String,
+}
+
+#[tracing::instrument(
+    name = "Revoke all sessions from a notification",
+    skip(request, form, pool)
+)]
+pub async fn revoke_sessions(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(revoked) = revoke_sessions_with_token(&form.token, &pool)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().body(INVALID_LINK_MESSAGE));
+    };
+    let event = AuditEvent::new(AuditAction::SessionRevoked, Some(revoked.user_id), &request)
+        .with_details(serde_json::json!({
+            "revoked": revoked.revoked,
+            "method": "notification_email",
+        }));
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Logged out everywhere</title>
+</head>
+<body>
+    <p>{} session(s) have been logged out.</p>
+    <p><a href="/login">Log in</a> and change your password.</p>
+</body>
+</html>"#,
+            revoked.revoked
+        )))
+}
@@ -0,0 +1,70 @@
+//! src/routes/setup/get.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::is_valid_setup_token;
+use crate::bootstrap::{has_enabled_users, SetupToken};
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    token: Option<String>,
+}
+
+pub async fn setup_form(
+    query: web::Query<QueryParams>,
+    expected_token: web::Data<SetupToken>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    if has_enabled_users(&pool).await.map_err(e500)? {
+        return Ok(see_other("/login"));
+    }
+    let token = query.0.token.unwrap_or_default();
+    if !is_valid_setup_token(&expected_token, &token) {
+        return Ok(HttpResponse::Forbidden().body("Invalid setup token."));
+    }
+    let csrf_token = session.csrf_token()?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Setup</title>
+</head>
+<body>
+    {msg_html}
+    <p>Create the owner account of this deployment.</p>
+    <form action="/setup" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <input type="hidden" name="setup_token" value="{token}">
+        <label>Username
+            <input type="text" placeholder="Enter Username" name="username">
+        </label>
+        <br>
+        <label>Password
+            <input type="password" placeholder="Enter Password" name="password">
+        </label>
+        <br>
+        <label>Confirm password
+            <input type="password" placeholder="Type the password again" name="password_check">
+        </label>
+        <br>
+        <button type="submit">Create owner</button>
+    </form>
+</body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,16 @@
+//! src/routes/setup/mod.rs
+
+mod get;
+mod post;
+
+pub use get::setup_form;
+pub use post::setup;
+
+use secrecy::ExposeSecret;
+
+use crate::authentication::middleware::constant_time_eq;
+use crate::bootstrap::SetupToken;
+
+fn is_valid_setup_token(expected: &SetupToken, submitted: &str) -> bool {
+    constant_time_eq(expected.expose_secret(), submitted)
+}
@@ -0,0 +1,65 @@
+//! src/routes/setup/post.rs
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use super::is_valid_setup_token;
+use crate::authentication::{check_password_policy, PasswordHashing};
+use crate::bootstrap::{create_owner, SetupToken};
+use crate::telemetry::spawn_blocking_with_tracing;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    setup_token: String,
+    username: String,
+    password: Secret<String>,
+    password_check: Secret<String>,
+}
+
+#[tracing::instrument(skip(form, expected_token, pool, hashing), fields(username = %form.username))]
+pub async fn setup(
+    form: web::Form<FormData>,
+    expected_token: web::Data<SetupToken>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !is_valid_setup_token(&expected_token, &form.setup_token) {
+        return Ok(HttpResponse::Forbidden().body("Invalid setup token."));
+    }
+    let retry_location = format!("/setup?token={}", form.setup_token);
+
+    let username = form.username.trim().to_owned();
+    if username.is_empty() {
+        FlashMessage::error("The username cannot be empty.").send();
+        return Ok(see_other(&retry_location));
+    }
+    if form.password.expose_secret() != form.password_check.expose_secret() {
+        FlashMessage::error("You entered two different passwords - the field values must match.")
+            .send();
+        return Ok(see_other(&retry_location));
+    }
+    let password = form.password.clone();
+    let policy_username = username.clone();
+    let policy_check =
+        spawn_blocking_with_tracing(move || check_password_policy(&password, &policy_username))
+            .await
+            .map_err(e500)?;
+    if let Err(rejection) = policy_check {
+        FlashMessage::error(rejection.to_string()).send();
+        return Ok(see_other(&retry_location));
+    }
+
+    let created = create_owner(&username, None, form.0.password, &pool, &hashing)
+        .await
+        .map_err(e500)?;
+    match created {
+        Some(_) => {
+            FlashMessage::info("The owner account has been created. You can now log in.").send()
+        }
+        None => FlashMessage::error("Setup has already been completed.").send(),
+    }
+    Ok(see_other("/login"))
+}
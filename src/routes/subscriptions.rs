@@ -1,14 +1,17 @@
 //! src/routes/subscriptions.rs
 
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse, ResponseError};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
 use anyhow::Context;
 use chrono::Utc;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::bot_protection::{BotCheck, BotProtection};
 use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionToken};
 use crate::email_client::{EmailClient, SendEmailError};
+use crate::rate_limit::{too_many_requests, RateLimitDecision, RateLimiter};
 use crate::startup::ApplicationBaseUrl;
 use crate::utils::error_chain_fmt;
 
@@ -102,6 +105,21 @@ impl ResponseError for SubscribeError {
 pub struct FormData {
     email: String,
     name: String,
+    /// The honeypot: hidden from people, so only bots fill it in.
+    website: Option<String>,
+    form_token: Option<String>,
+    pow_nonce: Option<String>,
+}
+
+impl FormData {
+    fn bot_check(&self) -> BotCheck<'_> {
+        BotCheck {
+            email: &self.email,
+            honeypot: self.website.as_deref(),
+            form_token: self.form_token.as_deref(),
+            proof_of_work_nonce: self.pow_nonce.as_deref(),
+        }
+    }
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -120,19 +138,42 @@ impl TryFrom<FormData> for NewSubscriber {
 
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_client, base_url),
+    skip(request, form, pool, email_client, base_url, bot_protection, rate_limiter),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
     )
 )]
 pub async fn subscribe(
+    request: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
+    bot_protection: web::Data<BotProtection>,
+    rate_limiter: web::Data<RateLimiter>,
 ) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    let bot_check = form.bot_check();
+    if bot_check.is_honeypot_filled() {
+        // Looking successful gives the bot no reason to try harder.
+        tracing::warn!("Ignored a subscription with the honeypot filled in.");
+        return Ok(HttpResponse::Ok().finish());
+    }
+    bot_protection
+        .check(&bot_check)
+        .map_err(|e| SubscribeError::ValidationError(e.to_string()))?;
+
+    let new_subscriber: NewSubscriber =
+        form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    // Every subscription sends an email: the address must not be flooded.
+    let key = format!(
+        "subscriptions:email:{}",
+        new_subscriber.email.as_ref().to_lowercase()
+    );
+    let limit = rate_limiter.settings().subscriptions_per_email;
+    if let RateLimitDecision::Limited { retry_after } = rate_limiter.check(&key, limit).await {
+        return Ok(too_many_requests(retry_after));
+    }
 
     let mut subscriber_id = get_subscriber_id_from_email(&pool, &new_subscriber)
         .await
@@ -153,6 +194,12 @@ pub async fn subscribe(
             let new_subscriber_id = insert_subscriber(&new_subscriber, &mut transaction)
                 .await
                 .context("Failed to insert new subscriber in the database.")?;
+            let event = AuditEvent::new(AuditAction::SubscriberCreated, None, &request)
+                .with_details(serde_json::json!({
+                    "subscriber_id": new_subscriber_id,
+                    "email": new_subscriber.email.as_ref(),
+                }));
+            record_audit_event(&mut *transaction, event).await?;
             subscriber_id = Some(new_subscriber_id);
         }
 
@@ -1,10 +1,11 @@
 //! src/routes/subscriptions_confirm.rs
 
-use actix_web::{http::StatusCode, web, HttpResponse, ResponseError};
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, ResponseError};
 use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
 use crate::{domain::SubscriptionToken, utils::error_chain_fmt};
 
 #[derive(thiserror::Error)]
@@ -46,8 +47,9 @@ impl TryFrom<Parameters> for SubscriptionToken {
     }
 }
 
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(parameters, pool))]
+#[tracing::instrument(name = "Confirm a pending subscriber", skip(request, parameters, pool))]
 pub async fn confirm(
+    request: HttpRequest,
     parameters: web::Query<Parameters>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, ConfirmationError> {
@@ -67,6 +69,9 @@ pub async fn confirm(
             confirm_subscriber(&pool, subscriber_id)
                 .await
                 .context("Failed to confirm subscriber")?;
+            let event = AuditEvent::new(AuditAction::SubscriberConfirmed, None, &request)
+                .with_details(serde_json::json!({ "subscriber_id": subscriber_id }));
+            record_audit_event(pool.get_ref(), event).await?;
             Ok(HttpResponse::Ok().finish())
         }
     }
@@ -0,0 +1,78 @@
+//! src/routes/subscriptions_form.rs
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+
+use crate::bot_protection::BotProtection;
+
+/// Solves the proof-of-work challenge in the browser before the form is sent.
+const PROOF_OF_WORK_SCRIPT: &str = r#"<script>
+        function leadingZeroBits(bytes) {
+            let bits = 0;
+            for (const byte of bytes) {
+                if (byte !== 0) {
+                    return bits + Math.clz32(byte) - 24;
+                }
+                bits += 8;
+            }
+            return bits;
+        }
+        const form = document.getElementById("subscribe");
+        form.addEventListener("submit", async (event) => {
+            if (form.pow_nonce.value !== "") {
+                return;
+            }
+            event.preventDefault();
+            const difficulty = Number(form.dataset.difficulty);
+            const encoder = new TextEncoder();
+            for (let nonce = 0; ; nonce++) {
+                const challenge = form.form_token.value + ":" + form.email.value;
+                const input = encoder.encode(challenge + ":" + nonce);
+                const digest = await crypto.subtle.digest("SHA-256", input);
+                if (leadingZeroBits(new Uint8Array(digest)) >= difficulty) {
+                    form.pow_nonce.value = nonce;
+                    form.submit();
+                    return;
+                }
+            }
+        });
+    </script>"#;
+
+pub async fn subscribe_form(bot_protection: web::Data<BotProtection>) -> HttpResponse {
+    let form_token = bot_protection.issue_form_token();
+    let difficulty = bot_protection.proof_of_work_difficulty();
+    let script = if difficulty > 0 {
+        PROOF_OF_WORK_SCRIPT
+    } else {
+        ""
+    };
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Subscribe</title>
+    <style>.website {{ display: none; }}</style>
+</head>
+<body>
+    <form id="subscribe" action="/subscriptions" method="post" data-difficulty="{difficulty}">
+        <label>Name
+            <input type="text" placeholder="Enter your name" name="name">
+        </label>
+        <label>Email
+            <input type="email" placeholder="Enter your email address" name="email">
+        </label>
+        <label class="website" aria-hidden="true">Leave this field empty
+            <input type="text" name="website" tabindex="-1" autocomplete="off">
+        </label>
+        <input type="hidden" name="form_token" value="{form_token}">
+        <input type="hidden" name="pow_nonce" value="">
+        <button type="submit">Subscribe</button>
+    </form>
+    {script}
+</body>
+</html>"#,
+        ))
+}
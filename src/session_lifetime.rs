@@ -0,0 +1,169 @@
+//! src/session_lifetime.rs
+//!
+//! actix-session gives every session the same lifetime. Sessions opened with
+//! "remember me" ticked outlive the others, both in the store and in the
+//! browser, and these are the pieces that stretch them.
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_session::SessionExt;
+use actix_web::body::MessageBody;
+use actix_web::cookie::{time::Duration, Cookie};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, SET_COOKIE};
+use actix_web::middleware::Next;
+use actix_web::web;
+use anyhow::Context;
+use std::collections::HashMap;
+
+use crate::configuration::SessionSettings;
+use crate::session_state::{TypedSession, SESSION_COOKIE_NAME};
+
+type SessionState = HashMap<String, String>;
+
+/// How long remembered sessions last, both in the store and in the browser.
+#[derive(Copy, Clone)]
+pub struct RememberMeLifetime(Duration);
+
+impl TryFrom<&SessionSettings> for RememberMeLifetime {
+    type Error = anyhow::Error;
+
+    fn try_from(settings: &SessionSettings) -> Result<Self, Self::Error> {
+        let lifetime = Duration::try_from(settings.lifetime(true).absolute_timeout)
+            .context("The remember me lifetime is out of range.")?;
+        Ok(Self(lifetime))
+    }
+}
+
+/// A session store that keeps remembered sessions for `remember_me_ttl`,
+/// rather than the TTL the session middleware asks for.
+#[derive(Clone)]
+pub struct RememberMeStore<S> {
+    inner: S,
+    remember_me_ttl: Duration,
+}
+
+impl<S: SessionStore> RememberMeStore<S> {
+    pub fn new(inner: S, remember_me_ttl: RememberMeLifetime) -> Self {
+        Self {
+            inner,
+            remember_me_ttl: remember_me_ttl.0,
+        }
+    }
+
+    fn ttl_for(&self, session_state: &SessionState, ttl: &Duration) -> Duration {
+        if is_remembered(session_state) {
+            self.remember_me_ttl
+        } else {
+            *ttl
+        }
+    }
+}
+
+fn is_remembered(session_state: &SessionState) -> bool {
+    session_state
+        .get(TypedSession::REMEMBER_ME_KEY)
+        .is_some_and(|value| value == "true")
+}
+
+impl<S: SessionStore> SessionStore for RememberMeStore<S> {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        self.inner.load(session_key).await
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let ttl = self.ttl_for(&session_state, ttl);
+        self.inner.save(session_state, &ttl).await
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let ttl = self.ttl_for(&session_state, ttl);
+        self.inner.update(session_key, session_state, &ttl).await
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        // Only the state tells whether the session is remembered.
+        let ttl = match self.inner.load(session_key).await? {
+            Some(session_state) => self.ttl_for(&session_state, ttl),
+            None => *ttl,
+        };
+        self.inner.update_ttl(session_key, &ttl).await
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        self.inner.delete(session_key).await
+    }
+}
+
+/// Set on responses to requests made within a remembered session.
+#[derive(Copy, Clone)]
+struct RememberedSession;
+
+/// Flag the responses of remembered sessions for `persist_remembered_sessions`.
+///
+/// It has to be registered inside the session middleware, which takes the
+/// session state away once the response is ready.
+pub async fn flag_remembered_sessions(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let mut response = next.call(req).await?;
+    let session = response.request().get_session();
+    if let Ok(Some(true)) = session.get::<bool>(TypedSession::REMEMBER_ME_KEY) {
+        response
+            .response_mut()
+            .extensions_mut()
+            .insert(RememberedSession);
+    }
+    Ok(response)
+}
+
+/// Turn the session cookie of remembered sessions into a persistent cookie,
+/// which survives the browser being closed.
+///
+/// It has to be registered outside the session middleware, which sets the cookie.
+pub async fn persist_remembered_sessions(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let lifetime = req.app_data::<web::Data<RememberMeLifetime>>().cloned();
+    let mut response = next.call(req).await?;
+    let remembered = response
+        .response()
+        .extensions()
+        .get::<RememberedSession>()
+        .is_some();
+    let Some(lifetime) = lifetime.filter(|_| remembered) else {
+        return Ok(response);
+    };
+    let max_age = lifetime.0;
+
+    let headers = response.headers_mut();
+    let cookies: Vec<HeaderValue> = headers.get_all(SET_COOKIE).cloned().collect();
+    headers.remove(SET_COOKIE);
+    for value in cookies {
+        let persistent = value
+            .to_str()
+            .ok()
+            .and_then(|value| Cookie::parse(value).ok())
+            .filter(|cookie| cookie.name() == SESSION_COOKIE_NAME && !cookie.value().is_empty())
+            .and_then(|mut cookie| {
+                cookie.set_max_age(max_age);
+                HeaderValue::from_str(&cookie.to_string()).ok()
+            });
+        headers.append(SET_COOKIE, persistent.unwrap_or(value));
+    }
+    Ok(response)
+}
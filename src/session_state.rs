@@ -2,13 +2,28 @@
 
 use actix_session::{Session, SessionExt, SessionGetError, SessionInsertError};
 use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
 use std::future::{ready, Ready};
 use uuid::Uuid;
 
+use crate::authentication::PendingOidcLogin;
+use crate::utils::random_token;
+
+/// The name of the cookie carrying the session key.
+pub const SESSION_COOKIE_NAME: &str = "id";
+
 pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const SESSION_ID_KEY: &'static str = "session_id";
+    const CSRF_TOKEN_KEY: &'static str = "csrf_token";
+    const OIDC_LOGIN_KEY: &'static str = "oidc_login";
+    const EXPIRES_AT_KEY: &'static str = "expires_at";
+    const AUTHENTICATED_AT_KEY: &'static str = "authenticated_at";
+    /// Read straight from the session state by the session store, which does
+    /// not go through `TypedSession`.
+    pub const REMEMBER_ME_KEY: &'static str = "remember_me";
 
     pub fn renew(&self) {
         self.0.renew();
@@ -22,6 +37,79 @@ impl TypedSession {
         self.0.get(Self::USER_ID_KEY)
     }
 
+    pub fn insert_session_id(&self, session_id: Uuid) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::SESSION_ID_KEY, session_id)
+    }
+
+    pub fn get_session_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::SESSION_ID_KEY)
+    }
+
+    /// When the session ends, however active it is.
+    pub fn insert_expires_at(&self, expires_at: DateTime<Utc>) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::EXPIRES_AT_KEY, expires_at)
+    }
+
+    pub fn get_expires_at(&self) -> Result<Option<DateTime<Utc>>, SessionGetError> {
+        self.0.get(Self::EXPIRES_AT_KEY)
+    }
+
+    /// When the user last entered their password, at login or since.
+    pub fn insert_authenticated_at(
+        &self,
+        authenticated_at: DateTime<Utc>,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::AUTHENTICATED_AT_KEY, authenticated_at)
+    }
+
+    pub fn get_authenticated_at(&self) -> Result<Option<DateTime<Utc>>, SessionGetError> {
+        self.0.get(Self::AUTHENTICATED_AT_KEY)
+    }
+
+    pub fn insert_remember_me(&self, remember_me: bool) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::REMEMBER_ME_KEY, remember_me)
+    }
+
+    pub fn get_remember_me(&self) -> Result<bool, SessionGetError> {
+        Ok(self.0.get(Self::REMEMBER_ME_KEY)?.unwrap_or(false))
+    }
+
+    /// Whether nothing at all is stored in the session, as when its state has
+    /// expired from the store.
+    pub fn is_empty(&self) -> bool {
+        self.0.entries().is_empty()
+    }
+
+    /// The token every form rendered for this session must submit back.
+    ///
+    /// It is generated the first time a form is rendered.
+    pub fn csrf_token(&self) -> Result<String, actix_web::Error> {
+        if let Some(token) = self.get_csrf_token()? {
+            return Ok(token);
+        }
+        let token = random_token(32);
+        self.0.insert(Self::CSRF_TOKEN_KEY, &token)?;
+        Ok(token)
+    }
+
+    pub fn get_csrf_token(&self) -> Result<Option<String>, SessionGetError> {
+        self.0.get(Self::CSRF_TOKEN_KEY)
+    }
+
+    pub fn insert_pending_oidc_login(
+        &self,
+        pending: &PendingOidcLogin,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::OIDC_LOGIN_KEY, pending)
+    }
+
+    /// The sign-in started with the identity provider, if any.
+    ///
+    /// It is removed from the session: each one can be completed only once.
+    pub fn take_pending_oidc_login(&self) -> Option<PendingOidcLogin> {
+        self.0.remove_as(Self::OIDC_LOGIN_KEY).and_then(Result::ok)
+    }
+
     pub fn log_out(self) {
         self.0.purge()
     }
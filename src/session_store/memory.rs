@@ -0,0 +1,87 @@
+//! src/session_store/memory.rs
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::{generate_session_key, SessionState};
+
+/// Keeps sessions in the memory of the process: they are lost on restart and
+/// not shared between instances, which makes it a fit for tests only.
+#[derive(Clone, Default)]
+pub struct MemorySessionStore {
+    sessions: Arc<Mutex<HashMap<String, (SessionState, Instant)>>>,
+}
+
+fn expiry(ttl: &Duration) -> Instant {
+    Instant::now() + std::time::Duration::try_from(*ttl).unwrap_or_default()
+}
+
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let sessions = self.sessions.lock().unwrap();
+        Ok(sessions
+            .get(session_key.as_ref())
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(state, _)| state.clone()))
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let session_key = generate_session_key();
+        let mut sessions = self.sessions.lock().unwrap();
+        // Saving is rare enough to sweep expired sessions along the way.
+        let now = Instant::now();
+        sessions.retain(|_, (_, expires_at)| *expires_at > now);
+        sessions.insert(
+            session_key.as_ref().to_owned(),
+            (session_state, expiry(ttl)),
+        );
+        Ok(session_key)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        {
+            let mut sessions = self.sessions.lock().unwrap();
+            if let Some(entry) = sessions
+                .get_mut(session_key.as_ref())
+                .filter(|(_, expires_at)| *expires_at > Instant::now())
+            {
+                *entry = (session_state, expiry(ttl));
+                return Ok(session_key);
+            }
+        }
+        // The session expired in the meantime: start a new one.
+        self.save(session_state, ttl).await.map_err(|e| match e {
+            SaveError::Serialization(e) => UpdateError::Serialization(e),
+            SaveError::Other(e) => UpdateError::Other(e),
+        })
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some((_, expires_at)) = sessions.get_mut(session_key.as_ref()) {
+            *expires_at = expiry(ttl);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        self.sessions.lock().unwrap().remove(session_key.as_ref());
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+//! src/session_store/mod.rs
+mod memory;
+mod postgres;
+
+pub use memory::MemorySessionStore;
+pub use postgres::PostgresSessionStore;
+
+use actix_session::storage::{
+    LoadError, RedisSessionStore, SaveError, SessionKey, SessionStore, UpdateError,
+};
+use actix_web::cookie::time::Duration;
+use anyhow::Context;
+use rand::distributions::{Alphanumeric, DistString};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::configuration::SessionStoreKind;
+use crate::metrics::metrics;
+
+type SessionState = HashMap<String, String>;
+
+/// The session store picked in the configuration.
+#[derive(Clone)]
+pub enum AnySessionStore {
+    Redis(Box<RedisSessionStore>),
+    Postgres(PostgresSessionStore),
+    Memory(MemorySessionStore),
+}
+
+impl AnySessionStore {
+    pub async fn new(
+        kind: SessionStoreKind,
+        redis_uri: Option<&Secret<String>>,
+        pool: &PgPool,
+    ) -> Result<Self, anyhow::Error> {
+        let store = match kind {
+            SessionStoreKind::Redis => {
+                let redis_uri =
+                    redis_uri.context("`redis_uri` must be set to keep sessions in Redis.")?;
+                Self::Redis(Box::new(
+                    RedisSessionStore::new(redis_uri.expose_secret()).await?,
+                ))
+            }
+            SessionStoreKind::Postgres => Self::Postgres(PostgresSessionStore::new(pool.clone())),
+            SessionStoreKind::Memory => Self::Memory(MemorySessionStore::default()),
+        };
+        Ok(store)
+    }
+}
+
+impl SessionStore for AnySessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let result = match self {
+            Self::Redis(store) => store.load(session_key).await,
+            Self::Postgres(store) => store.load(session_key).await,
+            Self::Memory(store) => store.load(session_key).await,
+        };
+        count_errors("load", result)
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let result = match self {
+            Self::Redis(store) => store.save(session_state, ttl).await,
+            Self::Postgres(store) => store.save(session_state, ttl).await,
+            Self::Memory(store) => store.save(session_state, ttl).await,
+        };
+        count_errors("save", result)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let result = match self {
+            Self::Redis(store) => store.update(session_key, session_state, ttl).await,
+            Self::Postgres(store) => store.update(session_key, session_state, ttl).await,
+            Self::Memory(store) => store.update(session_key, session_state, ttl).await,
+        };
+        count_errors("update", result)
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        let result = match self {
+            Self::Redis(store) => store.update_ttl(session_key, ttl).await,
+            Self::Postgres(store) => store.update_ttl(session_key, ttl).await,
+            Self::Memory(store) => store.update_ttl(session_key, ttl).await,
+        };
+        count_errors("update_ttl", result)
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        let result = match self {
+            Self::Redis(store) => store.delete(session_key).await,
+            Self::Postgres(store) => store.delete(session_key).await,
+            Self::Memory(store) => store.delete(session_key).await,
+        };
+        count_errors("delete", result)
+    }
+}
+
+fn count_errors<T, E>(operation: &str, result: Result<T, E>) -> Result<T, E> {
+    if result.is_err() {
+        metrics().record_session_store_error(operation);
+    }
+    result
+}
+
+/// A fresh session key, as long and random as the ones actix-session generates.
+fn generate_session_key() -> SessionKey {
+    Alphanumeric
+        .sample_string(&mut rand::thread_rng(), 64)
+        .try_into()
+        .expect("A 64 character key is within the session key size limits.")
+}
@@ -0,0 +1,161 @@
+//! src/session_store/postgres.rs
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use anyhow::Context;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+use super::{generate_session_key, SessionState};
+
+/// Keeps sessions in the `sessions` table, for deployments without Redis.
+///
+/// Expired rows are ignored when loading and swept by `delete_expired`.
+#[derive(Clone)]
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Delete the sessions that have expired, returning how many there were.
+    #[tracing::instrument(name = "Delete expired sessions", skip(self))]
+    pub async fn delete_expired(&self) -> Result<u64, anyhow::Error> {
+        let result = sqlx::query!("DELETE FROM sessions WHERE expires_at <= now()")
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete expired sessions.")?;
+        Ok(result.rows_affected())
+    }
+
+    /// Call `delete_expired` every `period`, until `shutdown` is cancelled.
+    pub async fn delete_expired_periodically(
+        self,
+        period: std::time::Duration,
+        shutdown: CancellationToken,
+    ) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            if let Err(e) = self.delete_expired().await {
+                tracing::warn!(error.cause_chain = ?e, "Failed to delete expired sessions.");
+            }
+        }
+    }
+
+    async fn insert(
+        &self,
+        session_state: &SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, anyhow::Error> {
+        let session_key = generate_session_key();
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (session_key, state, expires_at)
+            VALUES ($1, $2, now() + make_interval(secs => $3))
+            "#,
+            session_key.as_ref(),
+            Json(session_state) as _,
+            ttl.as_seconds_f64(),
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to save a session.")?;
+        Ok(session_key)
+    }
+}
+
+impl SessionStore for PostgresSessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let state = sqlx::query_scalar!(
+            r#"
+            SELECT state AS "state: Json<SessionState>"
+            FROM sessions
+            WHERE session_key = $1 AND expires_at > now()
+            "#,
+            session_key.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to load a session.")
+        .map_err(LoadError::Other)?;
+        Ok(state.map(|Json(state)| state))
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        self.insert(&session_state, ttl)
+            .await
+            .map_err(SaveError::Other)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET state = $2, expires_at = now() + make_interval(secs => $3)
+            WHERE session_key = $1 AND expires_at > now()
+            "#,
+            session_key.as_ref(),
+            Json(&session_state) as _,
+            ttl.as_seconds_f64(),
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to update a session.")
+        .map_err(UpdateError::Other)?;
+        if result.rows_affected() == 1 {
+            return Ok(session_key);
+        }
+        // The session expired in the meantime: start a new one.
+        self.insert(&session_state, ttl)
+            .await
+            .map_err(UpdateError::Other)
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET expires_at = now() + make_interval(secs => $2)
+            WHERE session_key = $1 AND expires_at > now()
+            "#,
+            session_key.as_ref(),
+            ttl.as_seconds_f64(),
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to extend a session.")?;
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "DELETE FROM sessions WHERE session_key = $1",
+            session_key.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to delete a session.")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+//! src/shutdown.rs
+//!
+//! Graceful shutdown: one token that every part of the application watches,
+//! and trackers of the requests and background tasks to wait for once it is
+//! cancelled.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+    requests: TaskTracker,
+}
+
+impl Shutdown {
+    /// Cancelled when the application starts shutting down.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Where to spawn background work that shutdown should wait for.
+    pub fn tasks(&self) -> TaskTracker {
+        self.tasks.clone()
+    }
+
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    /// Wait for every background task spawned so far, and refuse new ones.
+    pub async fn wait_for_tasks(&self) {
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+
+    /// Wait for the requests being handled to complete.
+    ///
+    /// Requests still arriving on open connections are served, but only
+    /// those already in flight are waited for.
+    pub async fn wait_for_requests(&self) {
+        self.requests.close();
+        self.requests.wait().await;
+    }
+}
+
+/// Count the request as in flight until a response is ready.
+///
+/// actix-web drops the connections a worker still holds if it stops before
+/// being told to drain them, so requests are waited for before it is stopped.
+pub async fn track_in_flight_requests(
+    shutdown: Data<Shutdown>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let _in_flight = shutdown.requests.token();
+    next.call(req).await
+}
+
+/// Resolve on Ctrl+C or, on Unix, on SIGTERM as sent by orchestrators.
+pub async fn wait_for_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C.");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM.")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
@@ -1,46 +1,116 @@
 //! src/startup.rs
 
-use crate::authentication::middleware::reject_anonymous_users;
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::authentication::middleware::{
+    reject_anonymous_users, reject_invalid_api_tokens, reject_invalid_csrf_tokens,
+    reject_non_owners, require_recent_authentication,
+};
+use crate::authentication::{OidcClient, PasswordHashing};
+use crate::bootstrap::{bootstrap, SetupToken};
+use crate::bot_protection::BotProtection;
+use crate::configuration::{DatabaseSettings, SessionSettings, SessionStoreKind, Settings};
 use crate::email_client::EmailClient;
+use crate::health::ReadinessChecks;
+use crate::metrics::{metrics_endpoint, record_http_metrics, MetricsToken};
+use crate::rate_limit::{
+    rate_limit_login, rate_limit_subscriptions, rate_limit_subscriptions_confirm, RateLimiter,
+};
+use crate::reload::{reload_on_hangup, LoadSettings, SettingsReloader};
+use crate::request_id::{propagate_request_id, RequestIdRootSpanBuilder};
 use crate::routes::{
-    admin_dashboard, change_password, change_password_form, confirm, health_check, home, log_out,
-    login, login_form, newsletter_form, publish_newsletter, subscribe,
+    active_sessions_page, admin_dashboard, api_list_subscribers, api_publish_newsletter,
+    api_tokens_page, audit_log, change_password, change_password_form, confirm, create_api_token,
+    export_audit_log, health_check, health_live, health_ready, home, log_level_page, log_out,
+    login, login_form, newsletter_form, oidc_callback, oidc_login, publish_newsletter,
+    raise_log_level, reauthenticate, reauthenticate_form, reload_settings, revert_log_level,
+    revoke_api_token, revoke_other_sessions, revoke_session, revoke_sessions, revoke_sessions_form,
+    runtime_settings_page, setup, setup_form, subscribe, subscribe_form,
+};
+use crate::session_lifetime::{
+    flag_remembered_sessions, persist_remembered_sessions, RememberMeLifetime, RememberMeStore,
 };
+use crate::session_state::SESSION_COOKIE_NAME;
+use crate::session_store::AnySessionStore;
+use crate::shutdown::{track_in_flight_requests, Shutdown};
+use crate::telemetry::LogFilter;
 
-use actix_session::storage::RedisSessionStore;
+use actix_session::config::{BrowserSession, TtlExtensionPolicy};
 use actix_session::SessionMiddleware;
-use actix_web::cookie::Key;
+use actix_web::cookie::{time, Key};
 use actix_web::middleware::from_fn;
 use actix_web::web::Data;
-use actix_web::{dev::Server, web, App, HttpServer};
+use actix_web::{dev::Server, guard, web, App, HttpServer};
 use actix_web_flash_messages::storage::CookieMessageStore;
 use actix_web_flash_messages::FlashMessagesFramework;
+use anyhow::Context;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing_actix_web::TracingLogger;
 
 pub struct Application {
     port: u16,
     server: Server,
+    metrics_port: Option<u16>,
+    metrics_server: Option<Server>,
+    setup_token: Option<SetupToken>,
+    connection_pool: PgPool,
+    shutdown: Shutdown,
+    shutdown_grace_period: Duration,
 }
 
 impl Application {
-    pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
+    /// `load_settings` reads the configuration again when it is reloaded,
+    /// and `log_filter` changes the filter of the global subscriber then.
+    pub async fn build(
+        configuration: Settings,
+        load_settings: LoadSettings,
+        log_filter: Option<LogFilter>,
+    ) -> Result<Self, anyhow::Error> {
+        configuration.validate()?;
+        let settings_reloader = Arc::new(SettingsReloader::new(
+            configuration.clone(),
+            load_settings,
+            log_filter,
+        ));
         let connection_pool = get_connection_pool(&configuration.database);
+        let shutdown = Shutdown::default();
+        let shutdown_grace_period = configuration.application.shutdown_grace_period();
 
         let sender_email = configuration
             .email_client
             .sender()
-            .expect("Invalid sender email address.");
+            .map_err(anyhow::Error::msg)?;
         let timeout = configuration.email_client.timeout();
         let email_client = EmailClient::new(
             configuration.email_client.base_url,
             sender_email,
             configuration.email_client.authorization_token,
-            timeout,
+            settings_reloader.email_timeout(),
+        );
+        let oidc_client = configuration.oidc.map(|settings| {
+            OidcClient::new(settings, &configuration.application.base_url, timeout)
+        });
+        let password_hashing = configuration
+            .password_hashing
+            .params()
+            .context("Invalid password hashing parameters.")
+            .and_then(PasswordHashing::new)?
+            .with_task_tracker(shutdown.tasks());
+        let setup_token = bootstrap(
+            configuration.bootstrap_owner,
+            &connection_pool,
+            &password_hashing,
+        )
+        .await
+        .context("Failed to bootstrap the first owner.")?;
+
+        let bot_protection = BotProtection::new(
+            settings_reloader.bot_protection(),
+            configuration.application.hmac_secret.clone(),
         );
 
         let address = format!(
@@ -49,82 +119,393 @@ impl Application {
         );
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr().unwrap().port();
+        // Metrics get their own listener when a port is set for them, so that
+        // they can be kept off the public network.
+        let metrics_listener = configuration
+            .application
+            .metrics_port
+            .map(|metrics_port| {
+                TcpListener::bind(format!(
+                    "{}:{}",
+                    configuration.application.host, metrics_port
+                ))
+            })
+            .transpose()?;
+        let metrics_port = metrics_listener
+            .as_ref()
+            .map(|listener| listener.local_addr().unwrap().port());
+        let metrics_token = configuration.application.metrics_token.map(MetricsToken);
+        // Without a port of its own, `/metrics` is only served alongside the
+        // application to scrapers presenting the token.
+        let (metrics_server, main_metrics_token) = match metrics_listener {
+            Some(listener) => (
+                Some(run_metrics_server(
+                    listener,
+                    connection_pool.clone(),
+                    metrics_token,
+                )?),
+                None,
+            ),
+            None => {
+                if metrics_token.is_none() {
+                    tracing::warn!(
+                        "`/metrics` is not served: set `application.metrics_port` or \
+                        `application.metrics_token`."
+                    );
+                }
+                (None, metrics_token)
+            }
+        };
         let server = run(
             listener,
-            connection_pool,
+            connection_pool.clone(),
             email_client,
             configuration.application.base_url,
             configuration.application.hmac_secret,
             configuration.redis_uri,
+            configuration.session,
+            password_hashing,
+            oidc_client,
+            setup_token.clone(),
+            bot_protection,
+            settings_reloader,
+            main_metrics_token,
+            shutdown.clone(),
+            shutdown_grace_period,
         )
         .await?;
-        Ok(Self { port, server })
+        Ok(Self {
+            port,
+            server,
+            metrics_port,
+            metrics_server,
+            setup_token,
+            connection_pool,
+            shutdown,
+            shutdown_grace_period,
+        })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    /// The port `/metrics` is served on, if not the application's own.
+    pub fn metrics_port(&self) -> Option<u16> {
+        self.metrics_port
+    }
+
+    /// The token giving access to `/setup`, if the application started without users.
+    pub fn setup_token(&self) -> Option<&SetupToken> {
+        self.setup_token.as_ref()
+    }
+
+    /// Cancel it to shut the application down gracefully.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.token()
+    }
+
+    /// Serve requests until the shutdown token is cancelled, then drain.
+    ///
+    /// New connections are refused and in-flight requests, a newsletter being
+    /// delivered included, get the grace period to complete. Background tasks
+    /// get the same period again before the connection pool is closed.
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+        let servers: Vec<_> = std::iter::once(&self.server)
+            .chain(&self.metrics_server)
+            .map(Server::handle)
+            .collect();
+        let token = self.shutdown.token();
+        let shutdown = self.shutdown.clone();
+        let grace_period = self.shutdown_grace_period;
+        let stop_servers = tokio::spawn(async move {
+            token.cancelled().await;
+            tracing::info!("Shutting down.");
+            for server in &servers {
+                server.pause().await;
+            }
+            // Workers can drop their connections as soon as they are stopped,
+            // so let in-flight requests complete first.
+            if tokio::time::timeout(grace_period, shutdown.wait_for_requests())
+                .await
+                .is_err()
+            {
+                tracing::warn!("Requests were still in flight at the end of the grace period.");
+            }
+            for server in servers {
+                server.stop(true).await;
+            }
+        });
+
+        let outcome = match self.metrics_server {
+            Some(metrics_server) => tokio::try_join!(self.server, metrics_server).map(|_| ()),
+            None => self.server.await,
+        };
+        // The servers may also have stopped on an error, with the rest still running.
+        self.shutdown.trigger();
+        let _ = stop_servers.await;
+        if tokio::time::timeout(self.shutdown_grace_period, self.shutdown.wait_for_tasks())
+            .await
+            .is_err()
+        {
+            tracing::warn!("Background tasks were still running at the end of the grace period.");
+        }
+        self.connection_pool.close().await;
+        outcome
     }
 }
 
+/// How often expired sessions are swept from the `sessions` table.
+const EXPIRED_SESSIONS_CLEANUP_PERIOD: Duration = Duration::from_secs(600);
+
 pub struct ApplicationBaseUrl(pub String);
 
 #[derive(Clone)]
 pub struct HmacSecret(pub Secret<String>);
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     listener: TcpListener,
     connection_pool: PgPool,
     email_client: EmailClient,
     base_url: String,
     hmac_secret: Secret<String>,
-    redis_uri: Secret<String>,
+    redis_uri: Option<Secret<String>>,
+    session_settings: SessionSettings,
+    password_hashing: PasswordHashing,
+    oidc_client: Option<OidcClient>,
+    setup_token: Option<SetupToken>,
+    bot_protection: BotProtection,
+    settings_reloader: Arc<SettingsReloader>,
+    metrics_token: Option<MetricsToken>,
+    shutdown: Shutdown,
+    shutdown_grace_period: Duration,
 ) -> Result<Server, anyhow::Error> {
     let connection_pool = Data::new(connection_pool);
     let email_client = Data::new(email_client);
     let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let password_hashing = Data::new(password_hashing);
+    let oidc_client = oidc_client.map(Data::new);
+    let setup_token = setup_token.map(Data::new);
+    let metrics_token = metrics_token.map(Data::new);
+    let bot_protection = Data::new(bot_protection);
+    let rate_limiter =
+        Data::new(RateLimiter::new(settings_reloader.rate_limiting(), redis_uri.as_ref()).await);
+    // Redis is only essential when it holds the sessions: rate limits fall back to memory.
+    let readiness_checks = Data::new(ReadinessChecks::new(
+        settings_reloader.health_check(),
+        redis_uri
+            .as_ref()
+            .filter(|_| matches!(session_settings.store, SessionStoreKind::Redis)),
+    )?);
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
-    let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
+    let session_store =
+        AnySessionStore::new(session_settings.store, redis_uri.as_ref(), &connection_pool).await?;
+    if let AnySessionStore::Postgres(store) = &session_store {
+        shutdown.tasks().spawn(
+            store
+                .clone()
+                .delete_expired_periodically(EXPIRED_SESSIONS_CLEANUP_PERIOD, shutdown.token()),
+        );
+    }
+    shutdown.tasks().spawn(reload_on_hangup(
+        settings_reloader.clone(),
+        connection_pool.get_ref().clone(),
+        shutdown.token(),
+    ));
+    let settings_reloader = Data::from(settings_reloader);
+    let remember_me_lifetime = RememberMeLifetime::try_from(&session_settings)?;
+    let session_store = RememberMeStore::new(session_store, remember_me_lifetime);
+    // The store drops sessions that go unused for the idle timeout.
+    let idle_timeout = time::Duration::try_from(session_settings.lifetime(false).idle_timeout)
+        .context("The session idle timeout is out of range.")?;
+    let session_settings = Data::new(session_settings);
+    let remember_me_lifetime = Data::new(remember_me_lifetime);
+    let shutdown = Data::new(shutdown);
 
     let server = HttpServer::new(move || {
         App::new()
             .wrap(message_framework.clone())
-            .wrap(SessionMiddleware::new(
-                redis_store.clone(),
-                secret_key.clone(),
-            ))
-            .wrap(TracingLogger::default())
+            .wrap(from_fn(flag_remembered_sessions))
+            .wrap(
+                SessionMiddleware::builder(session_store.clone(), secret_key.clone())
+                    .cookie_name(SESSION_COOKIE_NAME.into())
+                    .session_lifecycle(
+                        BrowserSession::default()
+                            .state_ttl(idle_timeout)
+                            .state_ttl_extension_policy(TtlExtensionPolicy::OnEveryRequest),
+                    )
+                    .build(),
+            )
+            .wrap(from_fn(persist_remembered_sessions))
+            .wrap(from_fn(propagate_request_id))
+            .wrap(TracingLogger::<RequestIdRootSpanBuilder>::new())
+            .wrap(from_fn(record_http_metrics))
+            .wrap(from_fn(track_in_flight_requests))
             .route("/", web::get().to(home))
             .route("/health_check", web::get().to(health_check))
-            .route("/login", web::get().to(login_form))
-            .route("/login", web::post().to(login))
-            .route("/subscriptions", web::post().to(subscribe))
-            .route("/subscriptions/confirm", web::get().to(confirm))
+            .route("/health/live", web::get().to(health_live))
+            .route("/health/ready", web::get().to(health_ready))
+            .configure(|cfg| {
+                if let Some(metrics_token) = &metrics_token {
+                    cfg.service(
+                        web::resource("/metrics")
+                            .app_data(metrics_token.clone())
+                            .route(web::get().to(metrics_endpoint)),
+                    );
+                }
+            })
+            .service(
+                web::resource("/login")
+                    .wrap(from_fn(reject_invalid_csrf_tokens))
+                    .route(web::get().to(login_form))
+                    .route(web::post().to(login).wrap(from_fn(rate_limit_login))),
+            )
+            .configure(|cfg| {
+                // Single sign-on is only offered when an identity provider is configured.
+                if let Some(oidc_client) = &oidc_client {
+                    cfg.app_data(oidc_client.clone())
+                        .route("/login/oidc", web::get().to(oidc_login))
+                        .route("/login/oidc/callback", web::get().to(oidc_callback));
+                }
+            })
+            .configure(|cfg| {
+                // The setup page only exists until the first owner is created.
+                if let Some(setup_token) = &setup_token {
+                    cfg.app_data(setup_token.clone()).service(
+                        web::resource("/setup")
+                            .wrap(from_fn(reject_invalid_csrf_tokens))
+                            .route(web::get().to(setup_form))
+                            .route(web::post().to(setup)),
+                    );
+                }
+            })
+            .service(
+                web::resource("/account/revoke-sessions")
+                    .wrap(from_fn(reject_invalid_csrf_tokens))
+                    .route(web::get().to(revoke_sessions_form))
+                    .route(web::post().to(revoke_sessions)),
+            )
+            .route("/subscriptions", web::get().to(subscribe_form))
+            .route(
+                "/subscriptions",
+                web::post()
+                    .to(subscribe)
+                    .wrap(from_fn(rate_limit_subscriptions)),
+            )
+            .route(
+                "/subscriptions/confirm",
+                web::get()
+                    .to(confirm)
+                    .wrap(from_fn(rate_limit_subscriptions_confirm)),
+            )
             .service(
                 web::scope("/admin")
+                    .wrap(from_fn(reject_invalid_csrf_tokens))
                     .wrap(from_fn(reject_anonymous_users))
                     .route("/dashboard", web::get().to(admin_dashboard))
-                    .route("/newsletters", web::get().to(newsletter_form))
-                    .route("/newsletters", web::post().to(publish_newsletter))
+                    .route("/reauthenticate", web::get().to(reauthenticate_form))
+                    .route("/reauthenticate", web::post().to(reauthenticate))
+                    .service(
+                        web::resource("/newsletters")
+                            .wrap(from_fn(require_recent_authentication))
+                            .route(web::get().to(newsletter_form))
+                            .route(web::post().to(publish_newsletter)),
+                    )
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
-                    .route("/logout", web::post().to(log_out)),
+                    .route("/logout", web::post().to(log_out))
+                    .route("/tokens", web::get().to(api_tokens_page))
+                    .service(
+                        web::resource("/tokens")
+                            .guard(guard::Post())
+                            .wrap(from_fn(require_recent_authentication))
+                            .to(create_api_token),
+                    )
+                    .route(
+                        "/tokens/{token_id}/revoke",
+                        web::post().to(revoke_api_token),
+                    )
+                    .route("/settings", web::get().to(runtime_settings_page))
+                    .route("/settings/reload", web::post().to(reload_settings))
+                    .service(
+                        web::scope("/debug")
+                            .wrap(from_fn(reject_non_owners))
+                            .route("/log-level", web::get().to(log_level_page))
+                            .route("/log-level", web::post().to(raise_log_level))
+                            .route("/log-level/revert", web::post().to(revert_log_level)),
+                    )
+                    .route("/sessions", web::get().to(active_sessions_page))
+                    .route(
+                        "/sessions/revoke-others",
+                        web::post().to(revoke_other_sessions),
+                    )
+                    .route(
+                        "/sessions/{session_id}/revoke",
+                        web::post().to(revoke_session),
+                    )
+                    .route("/audit", web::get().to(audit_log))
+                    .service(
+                        web::resource("/audit/export")
+                            .wrap(from_fn(require_recent_authentication))
+                            .route(web::get().to(export_audit_log)),
+                    ),
+            )
+            .service(
+                web::scope("/api/v1")
+                    .wrap(from_fn(reject_invalid_api_tokens))
+                    .route("/newsletters", web::post().to(api_publish_newsletter))
+                    .route("/subscribers", web::get().to(api_list_subscribers)),
             )
             .app_data(connection_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(password_hashing.clone())
+            .app_data(session_settings.clone())
+            .app_data(remember_me_lifetime.clone())
+            .app_data(bot_protection.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(readiness_checks.clone())
+            .app_data(shutdown.clone())
+            .app_data(settings_reloader.clone())
     })
+    // Signals are handled by whoever holds the shutdown token.
+    .disable_signals()
+    .shutdown_timeout(shutdown_grace_period.as_secs())
     .listen(listener)?
     .run();
 
     Ok(server)
 }
 
+/// A server for `/metrics` alone, for when it has a port of its own.
+///
+/// Scrapers only need a token if one is configured.
+pub fn run_metrics_server(
+    listener: TcpListener,
+    connection_pool: PgPool,
+    metrics_token: Option<MetricsToken>,
+) -> Result<Server, std::io::Error> {
+    let connection_pool = Data::new(connection_pool);
+    let metrics_token = metrics_token.map(Data::new);
+    let server = HttpServer::new(move || {
+        App::new()
+            .route("/metrics", web::get().to(metrics_endpoint))
+            .app_data(connection_pool.clone())
+            .configure(|cfg| {
+                if let Some(metrics_token) = &metrics_token {
+                    cfg.app_data(metrics_token.clone());
+                }
+            })
+    })
+    .disable_signals()
+    .listen(listener)?
+    .run();
+    Ok(server)
+}
+
 pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
     PgPoolOptions::new().connect_lazy_with(configuration.connection_options())
 }
@@ -1,12 +1,29 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use opentelemetry::propagation::Injector;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::task::JoinHandle;
 use tracing::{subscriber::set_global_default, Subscriber};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::fmt::MakeWriter;
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
+use tracing_subscriber::{layer::SubscriberExt, reload, EnvFilter, Registry};
+
+use crate::configuration::OpenTelemetrySettings;
 
 /// Compose multiple layers into a `tracing`'s subscriber.
 ///
+/// Spans are also exported to OpenTelemetry when a `tracer_provider` is given.
+/// The `LogFilter` returned alongside changes the filter of the subscriber.
+///
 /// # Implementation Notes
 ///
 /// We are using `impl Subscriber` as return type to avoid having to
@@ -19,17 +36,166 @@ pub fn get_subscriber<Sink>(
     name: String,
     env_filter: String,
     sink: Sink,
-) -> impl Subscriber + Send + Sync
+    tracer_provider: Option<&SdkTracerProvider>,
+) -> (impl Subscriber + Send + Sync, LogFilter)
 where
     Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
 {
-    let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
+    let directives = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|directives| EnvFilter::try_new(directives).is_ok())
+        .unwrap_or(env_filter);
+    let (env_filter, handle) = reload::Layer::new(EnvFilter::new(&directives));
+    let opentelemetry_layer = tracer_provider
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(name.clone())));
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
-    Registry::default()
+    let subscriber = Registry::default()
         .with(env_filter)
         .with(JsonStorageLayer)
         .with(formatting_layer)
+        .with(opentelemetry_layer);
+    let log_filter = LogFilter {
+        handle,
+        state: Arc::new(Mutex::new(LogFilterState {
+            directives,
+            raised: None,
+            generation: 0,
+        })),
+    };
+    (subscriber, log_filter)
+}
+
+/// Replaces the filter of a subscriber built by `get_subscriber` while it is in use.
+///
+/// On top of its directives, more can be added for a limited time, e.g. to
+/// debug a module in production without a restart.
+#[derive(Clone)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    state: Arc<Mutex<LogFilterState>>,
+}
+
+struct LogFilterState {
+    directives: String,
+    raised: Option<RaisedLogLevel>,
+    generation: u64,
+}
+
+/// Directives added on top of the configured ones, until they expire.
+#[derive(Clone, Debug)]
+pub struct RaisedLogLevel {
+    pub directives: String,
+    pub expires_at: DateTime<Utc>,
+    /// Tells the expiry of an earlier raise to leave the current one be.
+    generation: u64,
+}
+
+impl LogFilter {
+    /// Replace the configured directives, keeping the raised ones, if any.
+    pub fn set(&self, directives: &str) -> Result<(), anyhow::Error> {
+        EnvFilter::try_new(directives).context("Invalid log filter directives.")?;
+        let mut state = self.state.lock().unwrap();
+        state.directives = directives.to_owned();
+        self.apply(&state)
+    }
+
+    /// Add `directives` for `duration`, replacing those added before.
+    ///
+    /// It is up to the caller to call `expire` once `duration` has elapsed.
+    pub fn raise(
+        &self,
+        directives: &str,
+        duration: Duration,
+    ) -> Result<RaisedLogLevel, anyhow::Error> {
+        EnvFilter::try_new(directives).context("Invalid log filter directives.")?;
+        let expires_at = Utc::now()
+            + chrono::Duration::from_std(duration).context("The duration is too long.")?;
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        let raised = RaisedLogLevel {
+            directives: directives.to_owned(),
+            expires_at,
+            generation: state.generation,
+        };
+        state.raised = Some(raised.clone());
+        self.apply(&state)?;
+        Ok(raised)
+    }
+
+    /// Remove the directives added by the `raise` that returned `raised`.
+    ///
+    /// Returns `false` if they have since been replaced or reverted.
+    pub fn expire(&self, raised: &RaisedLogLevel) -> Result<bool, anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != raised.generation {
+            return Ok(false);
+        }
+        state.raised = None;
+        state.generation += 1;
+        self.apply(&state)?;
+        Ok(true)
+    }
+
+    /// Remove the directives added by `raise`, ahead of their expiry.
+    ///
+    /// Returns `false` if there were none.
+    pub fn revert(&self) -> Result<bool, anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.raised.is_none() {
+            return Ok(false);
+        }
+        state.raised = None;
+        state.generation += 1;
+        self.apply(&state)?;
+        Ok(true)
+    }
+
+    /// The directives in use, raised ones included.
+    pub fn directives(&self) -> String {
+        effective_directives(&self.state.lock().unwrap())
+    }
+
+    pub fn raised(&self) -> Option<RaisedLogLevel> {
+        self.state.lock().unwrap().raised.clone()
+    }
+
+    fn apply(&self, state: &LogFilterState) -> Result<(), anyhow::Error> {
+        // Both sets of directives are valid, and so is their combination.
+        let env_filter = EnvFilter::new(effective_directives(state));
+        self.handle
+            .reload(env_filter)
+            .context("Failed to replace the log filter.")
+    }
+}
+
+/// Later directives take precedence over earlier ones for the same target.
+fn effective_directives(state: &LogFilterState) -> String {
+    match &state.raised {
+        Some(raised) => format!("{},{}", state.directives, raised.directives),
+        None => state.directives.clone(),
+    }
+}
+
+/// Build a tracer provider exporting spans to the collector in `settings`.
+///
+/// It also makes W3C `traceparent` headers the way trace context is read from
+/// incoming requests and passed on to outgoing ones.
+/// The provider should be shut down before exiting, to flush pending spans.
+pub fn get_tracer_provider(
+    service_name: String,
+    settings: &OpenTelemetrySettings,
+) -> Result<SdkTracerProvider, anyhow::Error> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&settings.endpoint)
+        .build()
+        .context("Failed to build the OTLP span exporter.")?;
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build();
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(tracer_provider)
 }
 
 /// Register a subscriber as global default to process span data.
@@ -48,3 +214,70 @@ where
     let current_span = tracing::Span::current();
     tokio::task::spawn_blocking(move || current_span.in_scope(f))
 }
+
+/// Headers carrying the current span's trace context, for outgoing requests.
+///
+/// They are empty unless traces are exported.
+pub fn trace_context_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let context = tracing::Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    headers
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_subscriber;
+    use std::time::Duration;
+
+    #[test]
+    fn raised_directives_expire() {
+        let (_subscriber, log_filter) =
+            get_subscriber("test".into(), "info".into(), std::io::sink, None);
+
+        let raised = log_filter
+            .raise("zero2prod=debug", Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(log_filter.directives(), "info,zero2prod=debug");
+        log_filter.set("warn").unwrap();
+        assert_eq!(log_filter.directives(), "warn,zero2prod=debug");
+
+        assert!(log_filter.expire(&raised).unwrap());
+        assert_eq!(log_filter.directives(), "warn");
+        assert!(log_filter.raised().is_none());
+        assert!(!log_filter.expire(&raised).unwrap());
+    }
+
+    #[test]
+    fn an_earlier_raise_does_not_revert_a_later_one() {
+        let (_subscriber, log_filter) =
+            get_subscriber("test".into(), "info".into(), std::io::sink, None);
+
+        let earlier = log_filter
+            .raise("zero2prod=debug", Duration::from_secs(60))
+            .unwrap();
+        log_filter
+            .raise("zero2prod=trace", Duration::from_secs(60))
+            .unwrap();
+
+        assert!(!log_filter.expire(&earlier).unwrap());
+        assert_eq!(log_filter.directives(), "info,zero2prod=trace");
+        assert!(log_filter.revert().unwrap());
+        assert_eq!(log_filter.directives(), "info");
+    }
+}
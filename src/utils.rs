@@ -2,6 +2,8 @@
 
 use actix_web::http::header::LOCATION;
 use actix_web::HttpResponse;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
 
 pub fn e500<T>(e: T) -> actix_web::Error
 where
@@ -23,6 +25,16 @@ pub fn error_chain_fmt(
     Ok(())
 }
 
+/// A random string of `length` alphanumeric characters, for tokens and
+/// passwords that are handed out.
+pub fn random_token(length: usize) -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(length)
+        .collect()
+}
+
 pub fn see_other(location: &str) -> HttpResponse {
     HttpResponse::SeeOther()
         .insert_header((LOCATION, location))
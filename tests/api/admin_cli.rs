@@ -0,0 +1,272 @@
+//! tests/api/admin_cli.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Run `zero2prod-admin` against the test app's database and email server.
+async fn run_admin_cli(app: &TestApp, args: &[&str], stdin: Option<&str>) -> Output {
+    let database_name: String = sqlx::query_scalar!(r#"SELECT current_database() AS "name!""#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let stdin = stdin.map(ToOwned::to_owned);
+    let email_server_uri = app.email_server.uri();
+    tokio::task::spawn_blocking(move || {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_zero2prod-admin"))
+            .args(&args)
+            .env("APP_DATABASE__DATABASE_NAME", database_name)
+            .env("APP_EMAIL_CLIENT__BASE_URL", email_server_uri)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run zero2prod-admin.");
+        if let Some(stdin) = stdin {
+            writeln!(child.stdin.take().unwrap(), "{}", stdin).unwrap();
+        }
+        child.wait_with_output().unwrap()
+    })
+    .await
+    .unwrap()
+}
+
+async fn run_json(app: &TestApp, args: &[&str], stdin: Option<&str>) -> serde_json::Value {
+    let mut args = args.to_vec();
+    args.extend(["--output", "json"]);
+    let output = run_admin_cli(app, &args, stdin).await;
+    assert!(
+        output.status.success(),
+        "zero2prod-admin failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+async fn login(app: &TestApp, username: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({ "username": username, "password": password }))
+        .await
+}
+
+#[tokio::test]
+async fn a_created_user_can_log_in_with_the_generated_password() {
+    let app = spawn_app().await;
+
+    let report = run_json(&app, &["user", "create", "editor"], None).await;
+
+    assert_eq!(report["username"], "editor");
+    let password = report["generated_password"].as_str().unwrap();
+    assert_is_redirect_to(&login(&app, "editor", password).await, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn a_created_user_can_log_in_with_a_password_read_from_stdin() {
+    let app = spawn_app().await;
+
+    let report = run_json(
+        &app,
+        &[
+            "user",
+            "create",
+            "editor",
+            "--email",
+            "editor@example.com",
+            "--password-stdin",
+        ],
+        Some("a-long-enough-password"),
+    )
+    .await;
+
+    assert!(report["generated_password"].is_null());
+    let response = login(&app, "editor", "a-long-enough-password").await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn a_short_password_is_refused() {
+    let app = spawn_app().await;
+
+    let output = run_admin_cli(
+        &app,
+        &["user", "create", "editor", "--password-stdin"],
+        Some("short"),
+    )
+    .await;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid password length"));
+}
+
+#[tokio::test]
+async fn a_breached_password_is_refused() {
+    let app = spawn_app().await;
+
+    let output = run_admin_cli(
+        &app,
+        &[
+            "user",
+            "reset-password",
+            &app.test_user.username,
+            "--password-stdin",
+        ],
+        Some("qwertyuiop123"),
+    )
+    .await;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("list of breached passwords"));
+}
+
+#[tokio::test]
+async fn users_are_listed() {
+    let app = spawn_app().await;
+
+    let report = run_json(&app, &["user", "list"], None).await;
+
+    let users = report["users"].as_array().unwrap();
+    assert!(users
+        .iter()
+        .any(|u| u["username"] == app.test_user.username && u["disabled"] == false));
+
+    let output = run_admin_cli(&app, &["user", "list"], None).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&app.test_user.username));
+}
+
+#[tokio::test]
+async fn a_disabled_user_is_logged_out_and_cannot_log_in_again() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    run_json(&app, &["user", "disable", &app.test_user.username], None).await;
+
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+    let response = login(&app, &app.test_user.username, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn resetting_a_password_replaces_it_and_ends_sessions() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let report = run_json(
+        &app,
+        &["user", "reset-password", &app.test_user.username],
+        None,
+    )
+    .await;
+
+    assert_eq!(report["revoked_sessions"], 1);
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+    let response = login(&app, &app.test_user.username, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/login");
+    let password = report["generated_password"].as_str().unwrap();
+    let response = login(&app, &app.test_user.username, password).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn an_unknown_user_is_reported() {
+    let app = spawn_app().await;
+
+    let output = run_admin_cli(&app, &["user", "disable", "nobody"], None).await;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("There is no user named nobody."));
+}
+
+#[tokio::test]
+async fn subscribers_can_be_imported_confirmed_and_deleted() {
+    let app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let import_file = std::env::temp_dir().join(format!("{}.csv", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &import_file,
+        "email,name\n\
+        ada@example.com,Ada Lovelace\n\
+        ursula_le_guin@gmail.com,Ursula\n\
+        not-an-email,Nobody\n",
+    )
+    .unwrap();
+
+    let report = run_json(
+        &app,
+        &["subscriber", "import", import_file.to_str().unwrap()],
+        None,
+    )
+    .await;
+    std::fs::remove_file(&import_file).unwrap();
+    assert_eq!(report["imported"], 1);
+    assert_eq!(report["already_subscribed"], 1);
+    assert_eq!(report["invalid"][0]["line"], 4);
+
+    run_json(
+        &app,
+        &["subscriber", "confirm", "ursula_le_guin@gmail.com"],
+        None,
+    )
+    .await;
+    run_json(&app, &["subscriber", "delete", "ada@example.com"], None).await;
+
+    let subscribers = sqlx::query!("SELECT email, status FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(subscribers.len(), 1);
+    assert_eq!(subscribers[0].email, "ursula_le_guin@gmail.com");
+    assert_eq!(subscribers[0].status, "confirmed");
+}
+
+#[tokio::test]
+async fn migrate_is_idempotent() {
+    let app = spawn_app().await;
+
+    let report = run_json(&app, &["migrate"], None).await;
+
+    assert!(report["latest_migration"].is_number());
+}
+
+#[tokio::test]
+async fn a_test_email_is_sent_through_the_configured_provider() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let report = run_json(&app, &["send-test-email", "someone@example.com"], None).await;
+
+    assert_eq!(report["recipient"], "someone@example.com");
+}
+
+#[tokio::test]
+async fn a_config_file_is_applied_over_the_environment() {
+    let app = spawn_app().await;
+    let config_file = std::env::temp_dir().join(format!("{}.yaml", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &config_file,
+        "email_client:\n  sender_email: \"not-an-email\"\n",
+    )
+    .unwrap();
+
+    let output = run_admin_cli(
+        &app,
+        &["migrate", "--config", config_file.to_str().unwrap()],
+        None,
+    )
+    .await;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("email_client.sender_email"));
+    std::fs::remove_file(config_file).unwrap();
+}
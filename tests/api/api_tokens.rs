@@ -0,0 +1,160 @@
+//! tests/api/api_tokens.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use uuid::Uuid;
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+fn newsletter_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_api_tokens() {
+    let app = spawn_app().await;
+
+    let response = app.get_api_tokens().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_api_tokens(&[("name", "ci"), ("scopes", "newsletters:publish")])
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_created_token_is_shown_once_and_listed_by_name() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let token = app.create_api_token(&["newsletters:publish"]).await;
+    assert!(token.starts_with("z2p_"));
+
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("<td>ci</td>"));
+    assert!(!html_page.contains(&token));
+
+    let stored = sqlx::query!("SELECT token_hash FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_ne!(stored.token_hash, token);
+}
+
+#[tokio::test]
+async fn a_token_without_scopes_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_api_tokens(&[("name", "ci"), ("expires_in_days", "30")])
+        .await;
+    assert_is_redirect_to(&response, "/admin/tokens");
+
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("<p><i>Select at least one scope for the token.</i></p>"));
+}
+
+#[tokio::test]
+async fn requests_without_a_valid_token_are_rejected_with_a_401() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_api_newsletters(None, newsletter_request_body())
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.headers()["WWW-Authenticate"],
+        r#"Bearer realm="api""#
+    );
+
+    let response = app
+        .post_api_newsletters(Some("z2p_not-a-real-token"), newsletter_request_body())
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn a_scoped_token_can_publish_a_newsletter() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let token = app.create_api_token(&["newsletters:publish"]).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_api_newsletters(Some(&token), newsletter_request_body())
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn a_token_without_the_required_scope_is_rejected_with_a_403() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let token = app.create_api_token(&["subscribers:read"]).await;
+
+    let response = app
+        .post_api_newsletters(Some(&token), newsletter_request_body())
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.get_api_subscribers(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["subscribers"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn a_revoked_token_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let token = app.create_api_token(&["subscribers:read"]).await;
+    let token_id = sqlx::query!("SELECT token_id FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .token_id;
+
+    let response = app.post_revoke_api_token(token_id).await;
+    assert_is_redirect_to(&response, "/admin/tokens");
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("<p><i>The API token has been revoked.</i></p>"));
+
+    let response = app.get_api_subscribers(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn you_cannot_revoke_a_token_that_does_not_exist() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app.post_revoke_api_token(Uuid::new_v4()).await;
+    assert_is_redirect_to(&response, "/admin/tokens");
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("<p><i>No such API token.</i></p>"));
+}
+
+#[tokio::test]
+async fn an_expired_token_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let token = app.create_api_token(&["subscribers:read"]).await;
+
+    sqlx::query!("UPDATE api_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app.get_api_subscribers(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
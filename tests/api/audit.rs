@@ -0,0 +1,130 @@
+//! tests/api/audit.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+
+async fn recorded_actions(app: &TestApp) -> Vec<String> {
+    sqlx::query!("SELECT action FROM audit_events ORDER BY occurred_at")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.action)
+        .collect()
+}
+
+async fn get_audit_page(app: &TestApp, query: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/admin/audit{}", &app.address, query))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_audit_log() {
+    let app = spawn_app().await;
+
+    let response = get_audit_page(&app, "").await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = get_audit_page(&app, "/export").await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn logins_and_logouts_are_audited() {
+    let app = spawn_app().await;
+
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": "wrong-password"
+    }))
+    .await;
+    app.login_test_user().await;
+    app.post_logout().await;
+
+    assert_eq!(
+        recorded_actions(&app).await,
+        vec!["login.failed", "login.succeeded", "logout"]
+    );
+    let failed = sqlx::query!(
+        "SELECT actor_user_id, ip_address, details FROM audit_events WHERE action = 'login.failed'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(failed.actor_user_id, None);
+    assert_eq!(failed.ip_address.as_deref(), Some("127.0.0.1"));
+    assert_eq!(failed.details["username"], app.test_user.username);
+}
+
+#[tokio::test]
+async fn password_changes_are_audited_and_shown_in_the_log() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let new_password = Uuid::new_v4().to_string();
+
+    app.post_change_password(&serde_json::json!({
+        "current_password": &app.test_user.password,
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    }))
+    .await;
+
+    let html_page = get_audit_page(&app, "").await.text().await.unwrap();
+    assert!(html_page.contains("<td>password.changed</td>"));
+    assert!(html_page.contains(&format!("<td>{}</td>", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn the_audit_log_can_be_filtered_and_exported() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    app.create_api_token(&["subscribers:read"]).await;
+
+    let html_page = get_audit_page(&app, "?action=api_token.created&username=&since=&until=")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<td>api_token.created</td>"));
+    assert!(!html_page.contains("<td>login.succeeded</td>"));
+
+    let response = get_audit_page(&app, "/export?action=login.succeeded").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let events: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["action"], "login.succeeded");
+    assert_eq!(events[0]["actor_username"], app.test_user.username);
+
+    let response = get_audit_page(&app, "/export?username=someone-else").await;
+    let events: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert!(events.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_filters_are_rejected_with_a_400() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    for query in ["?action=not.an.action", "?since=yesterday"] {
+        let response = get_audit_page(&app, query).await;
+        assert_eq!(response.status().as_u16(), 400, "Query: {}", query);
+    }
+}
+
+#[tokio::test]
+async fn audit_events_cannot_be_rewritten() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let update = sqlx::query!("UPDATE audit_events SET action = 'logout'")
+        .execute(&app.db_pool)
+        .await;
+    assert!(update.is_err());
+    let delete = sqlx::query!("DELETE FROM audit_events")
+        .execute(&app.db_pool)
+        .await;
+    assert!(delete.is_err());
+}
@@ -1,6 +1,6 @@
 //! tests/api/change_password.rs
 
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
 use uuid::Uuid;
 
 #[tokio::test]
@@ -148,3 +148,52 @@ async fn current_password_must_be_valid() {
     let response = app.post_login(&login_body).await;
     assert_is_redirect_to(&response, "/admin/dashboard");
 }
+
+async fn change_password_to(app: &TestApp, new_password: &str) -> String {
+    app.login_test_user().await;
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": new_password,
+            "new_password_check": new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+    app.get_change_password_html().await
+}
+
+#[tokio::test]
+async fn a_breached_new_password_is_rejected() {
+    let app = spawn_app().await;
+
+    let html_page = change_password_to(&app, "Password1234").await;
+
+    assert!(html_page.contains(
+        "<p><i>This password appears in a list of breached passwords - please choose another one.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn new_password_must_not_contain_the_username() {
+    let app = spawn_app().await;
+    let new_password = format!("my-{}-password", app.test_user.username.to_uppercase());
+
+    let html_page = change_password_to(&app, &new_password).await;
+
+    assert!(html_page.contains("<p><i>The new password must not contain your username.</i></p>"));
+}
+
+#[tokio::test]
+async fn a_guessable_new_password_is_rejected_with_feedback() {
+    let app = spawn_app().await;
+
+    let html_page = change_password_to(&app, "monkeydragonbatman").await;
+
+    assert!(html_page.contains(
+        "<p><i>This password is too easy to guess. \
+        It is built around a common password - add a few uncommon words.</i></p>"
+    ));
+    // The old password still works.
+    app.post_logout().await;
+    app.login_test_user().await;
+}
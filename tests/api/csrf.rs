@@ -0,0 +1,112 @@
+//! tests/api/csrf.rs
+
+use crate::helpers::{
+    assert_is_redirect_to, build_client, extract_csrf_token, get_csrf_token, spawn_app,
+};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn login_without_a_csrf_token_is_rejected_with_a_403() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn an_admin_form_without_a_csrf_token_is_rejected_with_a_403() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    // The session is still alive.
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn a_csrf_token_from_another_session_is_rejected_with_a_403() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let attacker_token = get_csrf_token(&build_client("attacker"), &app.address).await;
+    let new_password = Uuid::new_v4().to_string();
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/password", &app.address))
+        .form(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+            "csrf_token": attacker_token,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.post_logout().await;
+    app.login_test_user().await;
+}
+
+#[tokio::test]
+async fn anonymous_users_are_redirected_before_the_csrf_check() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/newsletters", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn every_form_embeds_the_session_csrf_token() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let csrf_token = get_csrf_token(&app.api_client, &app.address).await;
+
+    for path in [
+        "/admin/dashboard",
+        "/admin/newsletters",
+        "/admin/password",
+        "/admin/tokens",
+        "/admin/sessions",
+    ] {
+        let html_page = app
+            .api_client
+            .get(format!("{}{}", &app.address, path))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(
+            extract_csrf_token(&html_page),
+            csrf_token,
+            "The form on {} did not embed the session's CSRF token",
+            path
+        );
+    }
+}
@@ -1,6 +1,9 @@
 //! tests/api/health_check.rs
 
-use crate::helpers::spawn_app;
+use crate::helpers::{maintenance_connection, spawn_app, spawn_app_with};
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::configuration::{get_configuration, SessionStoreKind};
 
 #[tokio::test]
 async fn health_check_works() {
@@ -16,3 +19,90 @@ async fn health_check_works() {
     assert!(response.status().is_success());
     assert_eq!(Some(0), response.content_length());
 }
+
+#[tokio::test]
+async fn the_liveness_check_works() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/health/live", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_readiness_check_reports_each_dependency() {
+    let app = spawn_app_with(|c| c.session.store = SessionStoreKind::Redis).await;
+
+    let response = app.get_health_ready().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["status"], "up");
+    assert_eq!(report["postgres"]["status"], "up");
+    assert!(report["postgres"]["latency_ms"].is_u64());
+    assert_eq!(report["redis"]["status"], "up");
+    assert!(report.get("email_provider").is_none());
+}
+
+#[tokio::test]
+async fn redis_is_not_checked_when_sessions_are_kept_elsewhere() {
+    let app = spawn_app().await;
+
+    let report: serde_json::Value = app.get_health_ready().await.json().await.unwrap();
+
+    assert_eq!(report["status"], "up");
+    assert!(report.get("redis").is_none());
+}
+
+#[tokio::test]
+async fn the_readiness_check_fails_when_postgres_is_unreachable() {
+    let app = spawn_app().await;
+    let database_name: String = sqlx::query_scalar!(r#"SELECT current_database() AS "name!""#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    app.db_pool.close().await;
+    let configuration = get_configuration().unwrap();
+    let mut maintenance = maintenance_connection(&configuration.database).await;
+    sqlx::query(&format!(
+        r#"ALTER DATABASE "{}" WITH ALLOW_CONNECTIONS false"#,
+        database_name
+    ))
+    .execute(&mut maintenance)
+    .await
+    .unwrap();
+    sqlx::query("SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE datname = $1")
+        .bind(&database_name)
+        .execute(&mut maintenance)
+        .await
+        .unwrap();
+
+    let response = app.get_health_ready().await;
+
+    assert_eq!(response.status().as_u16(), 503);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["status"], "down");
+    assert_eq!(report["postgres"]["status"], "down");
+}
+
+#[tokio::test]
+async fn the_email_provider_is_checked_when_enabled() {
+    let app = spawn_app_with(|c| c.health_check.check_email_provider = true).await;
+    let report: serde_json::Value = app.get_health_ready().await.json().await.unwrap();
+    assert_eq!(report["email_provider"]["status"], "up");
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+    let response = app.get_health_ready().await;
+
+    assert_eq!(response.status().as_u16(), 503);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["email_provider"]["status"], "down");
+}
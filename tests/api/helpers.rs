@@ -2,23 +2,32 @@
 
 use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+use secrecy::ExposeSecret;
 use secrecy::Secret;
+use sha2::{Digest, Sha256};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 use wiremock::MockServer;
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zero2prod::configuration::{
+    get_configuration, DatabaseSettings, OidcSettings, RateLimit, RateLimitStoreKind,
+    SessionStoreKind, Settings,
+};
 use zero2prod::startup::{get_connection_pool, Application};
-use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::telemetry::{get_subscriber, init_subscriber, LogFilter};
 
 static TRACING: LazyLock<()> = LazyLock::new(|| {
     let default_filter_level = "info".to_string();
     let subscriber_name = "test".to_string();
     if std::env::var("TEST_LOG").is_ok() {
-        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+        let (subscriber, _) =
+            get_subscriber(subscriber_name, default_filter_level, std::io::stdout, None);
         init_subscriber(subscriber);
     } else {
-        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+        let (subscriber, _) =
+            get_subscriber(subscriber_name, default_filter_level, std::io::sink, None);
         init_subscriber(subscriber);
     }
 });
@@ -38,9 +47,21 @@ pub struct TestApp {
     pub address: String,
     pub db_pool: PgPool,
     pub email_server: MockServer,
+    pub oidc_server: MockServer,
     pub port: u16,
+    /// Where `/metrics` is served, if it has a port of its own.
+    pub metrics_address: Option<String>,
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
+    pub setup_token: Option<String>,
+    /// Cancel to shut the application down gracefully.
+    pub shutdown: CancellationToken,
+    pub server: JoinHandle<Result<(), std::io::Error>>,
+    /// What the application reads when its settings are reloaded.
+    pub configuration: Arc<Mutex<Settings>>,
+    pub log_filter: LogFilter,
+    /// Keeps `log_filter` working: it stops once its subscriber is dropped.
+    _log_subscriber: Box<dyn tracing::Subscriber + Send + Sync>,
 }
 
 impl TestUser {
@@ -52,7 +73,17 @@ impl TestUser {
         }
     }
 
-    async fn store(&self, pool: &PgPool) {
+    pub async fn make_owner(&self, pool: &PgPool) {
+        sqlx::query!(
+            "UPDATE users SET role = 'owner' WHERE user_id = $1",
+            self.user_id
+        )
+        .execute(pool)
+        .await
+        .expect("Failed to make the test user an owner.");
+    }
+
+    pub async fn store(&self, pool: &PgPool) {
         let salt = SaltString::generate(&mut rand::thread_rng());
         let password_hash = Argon2::new(
             Algorithm::Argon2id,
@@ -78,7 +109,41 @@ impl TestUser {
 }
 
 impl TestApp {
+    /// Submit the subscribe form the way a browser would after rendering it,
+    /// i.e. with its form token and, if required, a solved proof of work.
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
+        let body = self.complete_subscribe_form(body).await;
+        self.post_raw_subscriptions(body).await
+    }
+
+    /// Add to `body` what rendering the subscribe form and solving its
+    /// proof of work would.
+    pub async fn complete_subscribe_form(&self, body: String) -> String {
+        let html_page = self.get_subscribe_form_html().await;
+        let form_token = extract_attribute(&html_page, r#"name="form_token" value=""#);
+        let difficulty: u8 = extract_attribute(&html_page, r#"data-difficulty=""#)
+            .parse()
+            .unwrap();
+        let mut body = body;
+        if !body.is_empty() {
+            body.push('&');
+        }
+        body.push_str(&serde_urlencoded::to_string([("form_token", &form_token)]).unwrap());
+        if difficulty > 0 {
+            let fields: Vec<(String, String)> = serde_urlencoded::from_str(&body).unwrap();
+            let email = fields
+                .iter()
+                .find(|(name, _)| name == "email")
+                .map_or("", |(_, value)| value.as_str());
+            let challenge = format!("{}:{}", form_token, email);
+            let nonce = solve_proof_of_work(&challenge, difficulty);
+            body.push_str(&format!("&pow_nonce={}", nonce));
+        }
+        body
+    }
+
+    /// Submit `body` to the subscribe endpoint as is, the way a script would.
+    pub async fn post_raw_subscriptions(&self, body: String) -> reqwest::Response {
         self.api_client
             .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
@@ -88,13 +153,19 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
-    pub async fn post_newsletters(&self, body: serde_json::Value) -> reqwest::Response {
+    pub async fn get_subscribe_form_html(&self) -> String {
         self.api_client
-            .post(format!("{}/admin/newsletters", &self.address))
-            .form(&body)
+            .get(format!("{}/subscriptions", &self.address))
             .send()
             .await
-            .expect("Failed to execute request")
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_newsletters(&self, body: serde_json::Value) -> reqwest::Response {
+        post_form(&self.api_client, &self.address, "/admin/newsletters", &body).await
     }
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
@@ -120,12 +191,7 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
-        self.api_client
-            .post(format!("{}/login", &self.address))
-            .form(body)
-            .send()
-            .await
-            .expect("Failed to execute request.")
+        post_form(&self.api_client, &self.address, "/login", body).await
     }
 
     pub async fn get_login_html(&self) -> String {
@@ -179,72 +245,440 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
+        post_form(&self.api_client, &self.address, "/admin/password", body).await
+    }
+
+    pub async fn get_reauthenticate_html(&self, next: &str) -> String {
         self.api_client
-            .post(format!("{}/admin/password", &self.address))
-            .form(body)
+            .get(format!("{}/admin/reauthenticate", &self.address))
+            .query(&[("next", next)])
             .send()
             .await
-            .expect("Failed to execute request")
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_reauthenticate(&self, password: &str, next: &str) -> reqwest::Response {
+        let body = serde_json::json!({ "password": password, "next": next });
+        post_form(
+            &self.api_client,
+            &self.address,
+            "/admin/reauthenticate",
+            &body,
+        )
+        .await
+    }
+
+    pub async fn get_api_tokens(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/tokens", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_tokens_html(&self) -> String {
+        self.get_api_tokens().await.text().await.unwrap()
+    }
+
+    pub async fn post_api_tokens<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        post_form(&self.api_client, &self.address, "/admin/tokens", body).await
+    }
+
+    /// Create an API token through the admin area and return its plain value.
+    pub async fn create_api_token(&self, scopes: &[&str]) -> String {
+        let mut body = vec![("name", "ci"), ("expires_in_days", "30")];
+        body.extend(scopes.iter().map(|scope| ("scopes", *scope)));
+        let html_page = self.post_api_tokens(&body).await.text().await.unwrap();
+        let start = html_page.find(r#"<code id="api-token">"#).unwrap() + 21;
+        let end = start + html_page[start..].find("</code>").unwrap();
+        html_page[start..end].to_owned()
+    }
+
+    pub async fn post_revoke_api_token(&self, token_id: Uuid) -> reqwest::Response {
+        let path = format!("/admin/tokens/{}/revoke", token_id);
+        post_form(&self.api_client, &self.address, &path, &()).await
+    }
+
+    pub async fn post_api_newsletters(
+        &self,
+        token: Option<&str>,
+        body: serde_json::Value,
+    ) -> reqwest::Response {
+        let mut request = reqwest::Client::new()
+            .post(format!("{}/api/v1/newsletters", &self.address))
+            .json(&body);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_subscribers(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/v1/subscribers", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions_html(&self) -> String {
+        self.get_sessions().await.text().await.unwrap()
+    }
+
+    pub async fn post_revoke_session(&self, session_id: Uuid) -> reqwest::Response {
+        let path = format!("/admin/sessions/{}/revoke", session_id);
+        post_form(&self.api_client, &self.address, &path, &()).await
+    }
+
+    pub async fn post_revoke_other_sessions(&self) -> reqwest::Response {
+        post_form(
+            &self.api_client,
+            &self.address,
+            "/admin/sessions/revoke-others",
+            &(),
+        )
+        .await
+    }
+
+    pub async fn get_runtime_settings(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/settings", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_runtime_settings_html(&self) -> String {
+        self.get_runtime_settings().await.text().await.unwrap()
+    }
+
+    /// Reload the settings from `self.configuration`.
+    pub async fn post_reload_settings(&self) -> reqwest::Response {
+        post_form(
+            &self.api_client,
+            &self.address,
+            "/admin/settings/reload",
+            &(),
+        )
+        .await
+    }
+
+    pub async fn get_log_level(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/debug/log-level", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_log_level_html(&self) -> String {
+        self.get_log_level().await.text().await.unwrap()
+    }
+
+    pub async fn post_raise_log_level(&self, directives: &str, minutes: u64) -> reqwest::Response {
+        let body = serde_json::json!({ "directives": directives, "minutes": minutes });
+        post_form(
+            &self.api_client,
+            &self.address,
+            "/admin/debug/log-level",
+            &body,
+        )
+        .await
+    }
+
+    pub async fn post_revert_log_level(&self) -> reqwest::Response {
+        post_form(
+            &self.api_client,
+            &self.address,
+            "/admin/debug/log-level/revert",
+            &(),
+        )
+        .await
+    }
+
+    /// Log the test user in from a separate cookie jar, as if from another device.
+    pub async fn login_from_another_device(&self, user_agent: &str) -> reqwest::Client {
+        let client = build_client(user_agent);
+        let body = serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password
+        });
+        let response = post_form(&client, &self.address, "/login", &body).await;
+        assert_is_redirect_to(&response, "/admin/dashboard");
+        client
+    }
+
+    pub async fn login_test_user(&self) {
+        let response = self
+            .post_login(&serde_json::json!({
+                "username": &self.test_user.username,
+                "password": &self.test_user.password
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/admin/dashboard");
+    }
+
+    /// Give the test user an email address, so that they receive security notifications.
+    pub async fn set_test_user_email(&self) -> String {
+        let email = format!("{}@example.com", Uuid::new_v4());
+        sqlx::query!(
+            "UPDATE users SET email = $1 WHERE user_id = $2",
+            email,
+            self.test_user.user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .expect("Failed to set the test user's email.");
+        email
     }
 
     pub async fn post_logout(&self) -> reqwest::Response {
+        post_form(&self.api_client, &self.address, "/admin/logout", &()).await
+    }
+
+    pub async fn get_health_ready(&self) -> reqwest::Response {
         self.api_client
-            .post(format!("{}/admin/logout", &self.address))
+            .get(format!("{}/health/ready", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
     }
+
+    /// Scrape `/metrics` from wherever it is served, with the configured token.
+    pub async fn get_metrics(&self) -> String {
+        let token = self
+            .configuration
+            .lock()
+            .unwrap()
+            .application
+            .metrics_token
+            .clone();
+        let response = self
+            .get_metrics_with(token.as_ref().map(|token| token.expose_secret().as_str()))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+        response.text().await.unwrap()
+    }
+
+    pub async fn get_metrics_with(&self, token: Option<&str>) -> reqwest::Response {
+        let address = self.metrics_address.as_ref().unwrap_or(&self.address);
+        let mut request = self.api_client.get(format!("{}/metrics", address));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
 }
 
 // Launch our application in the background and returns its address
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// `spawn_app`, with the configuration adjusted by `configure` first.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
+    let test_app = spawn_app_without_users(configure).await;
+    test_app.test_user.store(&test_app.db_pool).await;
+    test_app
+}
+
+/// Launch our application on an empty `users` table, as on a first deployment.
+///
+/// `test_user` is generated but not stored.
+pub async fn spawn_app_without_users(configure: impl FnOnce(&mut Settings)) -> TestApp {
     LazyLock::force(&TRACING);
 
     let email_server = MockServer::start().await;
+    let oidc_server = MockServer::start().await;
 
     let mut configuration = get_configuration().expect("Failed to read configuration.");
     configuration.database.database_name = Uuid::new_v4().to_string();
     configuration.application.port = 0;
     configuration.email_client.base_url = email_server.uri();
+    configuration.session.store = SessionStoreKind::Memory;
+    // Tests fill in forms faster than people do.
+    configuration.bot_protection.min_fill_seconds = 0;
+    // All test requests come from the same address.
+    configuration.rate_limiting.store = RateLimitStoreKind::Memory;
+    let unlimited = RateLimit {
+        capacity: 1000,
+        refill_seconds: 1,
+    };
+    configuration.rate_limiting.login = unlimited;
+    configuration.rate_limiting.subscriptions = unlimited;
+    configuration.rate_limiting.subscriptions_per_email = unlimited;
+    configuration.rate_limiting.subscriptions_confirm = unlimited;
+    configuration.application.metrics_token = Some(Secret::new(Uuid::new_v4().to_string()));
+    configuration.oidc = Some(OidcSettings {
+        issuer_url: oidc_server.uri(),
+        client_id: "zero2prod".into(),
+        client_secret: Secret::new("oidc-client-secret".into()),
+    });
+    configure(&mut configuration);
 
     configure_database(&configuration.database).await;
 
-    let application = Application::build(configuration.clone())
-        .await
-        .expect("Failed to build application");
+    let reloaded_configuration = Arc::new(Mutex::new(configuration.clone()));
+    let load_settings = {
+        let configuration = reloaded_configuration.clone();
+        Box::new(move || Ok(configuration.lock().unwrap().clone()))
+    };
+    // Logs go to the global subscriber: this one only gives the application
+    // a filter of its own to change.
+    let (log_subscriber, log_filter) = get_subscriber(
+        "test".into(),
+        configuration.log_filter.clone(),
+        std::io::sink,
+        None,
+    );
+    let application = Application::build(
+        configuration.clone(),
+        load_settings,
+        Some(log_filter.clone()),
+    )
+    .await
+    .expect("Failed to build application");
     let application_port = application.port();
+    let setup_token = application
+        .setup_token()
+        .map(|token| token.expose_secret().to_owned());
     let address = format!("http://127.0.0.1:{}", application_port);
-    std::mem::drop(tokio::spawn(application.run_until_stopped()));
+    let metrics_address = application
+        .metrics_port()
+        .map(|port| format!("http://127.0.0.1:{}", port));
+    let shutdown = application.shutdown_token();
+    let server = tokio::spawn(application.run_until_stopped());
 
-    let client = reqwest::Client::builder()
-        .redirect(reqwest::redirect::Policy::none())
-        .cookie_store(true)
-        .build()
-        .unwrap();
+    let client = build_client("zero2prod-tests");
 
-    let test_app = TestApp {
+    TestApp {
         address,
         db_pool: get_connection_pool(&configuration.database),
         email_server,
+        oidc_server,
         port: application_port,
+        metrics_address,
         test_user: TestUser::generate(),
         api_client: client,
-    };
-    test_app.test_user.store(&test_app.db_pool).await;
-    test_app
+        setup_token,
+        shutdown,
+        server,
+        configuration: reloaded_configuration,
+        log_filter,
+        _log_subscriber: Box::new(log_subscriber),
+    }
 }
 
-async fn configure_database(config: &DatabaseSettings) -> PgPool {
-    // Create database
+/// Submit a form the way a browser would after rendering one of our pages,
+/// i.e. including the session's CSRF token.
+pub async fn post_form<Body>(
+    client: &reqwest::Client,
+    address: &str,
+    path: &str,
+    body: &Body,
+) -> reqwest::Response
+where
+    Body: serde::Serialize,
+{
+    let csrf_token = get_csrf_token(client, address).await;
+    let mut body = serde_urlencoded::to_string(body).unwrap();
+    if !body.is_empty() {
+        body.push('&');
+    }
+    body.push_str(&serde_urlencoded::to_string([("csrf_token", csrf_token)]).unwrap());
+    client
+        .post(format!("{}{}", address, path))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// The session's CSRF token, as embedded in the login form.
+pub async fn get_csrf_token(client: &reqwest::Client, address: &str) -> String {
+    let html_page = client
+        .get(format!("{}/login", address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap();
+    extract_csrf_token(&html_page)
+}
+
+pub fn extract_csrf_token(html_page: &str) -> String {
+    extract_attribute(html_page, r#"name="csrf_token" value=""#)
+}
+
+/// The attribute value that follows `marker` in `html_page`.
+fn extract_attribute(html_page: &str, marker: &str) -> String {
+    let start = html_page
+        .find(marker)
+        .unwrap_or_else(|| panic!("No {} in the page.", marker))
+        + marker.len();
+    let end = start + html_page[start..].find('"').unwrap();
+    html_page[start..end].to_owned()
+}
+
+/// A nonce such that SHA-256 of `{challenge}:{nonce}` starts with `difficulty` zero bits.
+pub fn solve_proof_of_work(challenge: &str, difficulty: u8) -> u64 {
+    (0u64..)
+        .find(|nonce| {
+            let digest = Sha256::digest(format!("{}:{}", challenge, nonce).as_bytes());
+            let mut zero_bits = 0;
+            for byte in digest {
+                zero_bits += byte.leading_zeros();
+                if byte != 0 {
+                    break;
+                }
+            }
+            zero_bits >= u32::from(difficulty)
+        })
+        .unwrap()
+}
+
+pub fn build_client(user_agent: &str) -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .user_agent(user_agent)
+        .build()
+        .unwrap()
+}
+
+/// A superuser connection to the `postgres` database of the test server.
+pub async fn maintenance_connection(config: &DatabaseSettings) -> PgConnection {
     let maintenance_settings = DatabaseSettings {
         database_name: "postgres".to_string(),
         username: "postgres".to_string(),
         password: Secret::new("password".to_string()),
         ..config.clone()
     };
-    let mut connection = PgConnection::connect_with(&maintenance_settings.connection_options())
+    PgConnection::connect_with(&maintenance_settings.connection_options())
         .await
-        .expect("Failed to connect to Postgres");
+        .expect("Failed to connect to Postgres")
+}
+
+async fn configure_database(config: &DatabaseSettings) -> PgPool {
+    // Create database
+    let mut connection = maintenance_connection(config).await;
     connection
         .execute(format!(r#"CREATE DATABASE "{}";"#, config.database_name).as_str())
         .await
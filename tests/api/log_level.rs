@@ -0,0 +1,95 @@
+//! tests/api/log_level.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn only_owners_can_change_the_log_level() {
+    let app = spawn_app().await;
+
+    let response = app.get_log_level().await;
+    assert_is_redirect_to(&response, "/login");
+
+    app.login_test_user().await;
+    let response = app.get_log_level().await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_raise_log_level("zero2prod=debug", 15).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(app.log_filter.raised().is_none());
+}
+
+#[tokio::test]
+async fn an_owner_can_raise_the_log_level_for_a_while() {
+    let app = spawn_app().await;
+    app.test_user.make_owner(&app.db_pool).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_raise_log_level("zero2prod::email_client=debug", 15)
+        .await;
+    assert_is_redirect_to(&response, "/admin/debug/log-level");
+
+    let html_page = app.get_log_level_html().await;
+    assert!(html_page.contains("<p><i>The log level has been raised.</i></p>"));
+    assert!(html_page.contains("<code>info,zero2prod::email_client=debug</code>"));
+    let raised = app.log_filter.raised().unwrap();
+    let minutes_left = (raised.expires_at - chrono::Utc::now()).num_minutes();
+    assert!((14..=15).contains(&minutes_left));
+    let details =
+        sqlx::query!("SELECT details FROM audit_events WHERE action = 'log_level.raised'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .details;
+    assert_eq!(details["directives"], "zero2prod::email_client=debug");
+}
+
+#[tokio::test]
+async fn an_owner_can_revert_the_log_level_early() {
+    let app = spawn_app().await;
+    app.test_user.make_owner(&app.db_pool).await;
+    app.login_test_user().await;
+    app.post_raise_log_level("zero2prod=trace", 60).await;
+
+    let response = app.post_revert_log_level().await;
+    assert_is_redirect_to(&response, "/admin/debug/log-level");
+
+    let html_page = app.get_log_level_html().await;
+    assert!(html_page.contains("<p><i>The log level has been reverted.</i></p>"));
+    assert!(html_page.contains("<p>The log level is not raised.</p>"));
+    assert_eq!(app.log_filter.directives(), "info");
+}
+
+#[tokio::test]
+async fn invalid_requests_leave_the_log_level_as_it_is() {
+    let app = spawn_app().await;
+    app.test_user.make_owner(&app.db_pool).await;
+    app.login_test_user().await;
+
+    let test_cases = [
+        ("zero2prod=loud", 15, "Invalid log filter directives."),
+        ("", 15, "Enter the directives to add"),
+        (
+            "zero2prod=debug",
+            0,
+            "The log level can be raised for 1 to 60 minutes.",
+        ),
+        (
+            "zero2prod=debug",
+            61,
+            "The log level can be raised for 1 to 60 minutes.",
+        ),
+    ];
+    for (directives, minutes, error_message) in test_cases {
+        app.post_raise_log_level(directives, minutes).await;
+
+        let html_page = app.get_log_level_html().await;
+        assert!(
+            html_page.contains(error_message),
+            "No `{}` error for {}, {} minutes.",
+            error_message,
+            directives,
+            minutes
+        );
+        assert!(app.log_filter.raised().is_none());
+    }
+}
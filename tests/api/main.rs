@@ -1,10 +1,27 @@
 //! tests/api/main.rs
 
+mod admin_cli;
 mod admin_dashboard;
+mod api_tokens;
+mod audit;
 mod change_password;
+mod csrf;
 mod health_check;
 mod helpers;
+mod log_level;
 mod login;
+mod metrics;
 mod newsletter;
+mod oidc;
+mod rate_limiting;
+mod reauthentication;
+mod request_id;
+mod runtime_settings;
+mod security_notifications;
+mod session_lifetimes;
+mod session_stores;
+mod sessions;
+mod setup;
+mod shutdown;
 mod subscriptions;
 mod subscriptions_confirm;
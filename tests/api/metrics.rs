@@ -0,0 +1,150 @@
+//! tests/api/metrics.rs
+use crate::helpers::{spawn_app, spawn_app_with};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::configuration::SessionStoreKind;
+
+// Every test of this binary records into the same registry, so the tests
+// check that series exist and have moved rather than their exact values.
+
+/// The value of the series `series`, labels included, if it was exported.
+fn metric_value(metrics: &str, series: &str) -> Option<f64> {
+    metrics.lines().find_map(|line| {
+        let value = line.strip_prefix(series)?.strip_prefix(' ')?;
+        value.parse().ok()
+    })
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_and_status() {
+    let app = spawn_app().await;
+
+    app.api_client
+        .get(format!("{}/health_check", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let metrics = app.get_metrics().await;
+
+    let requests = metric_value(
+        &metrics,
+        r#"zero2prod_http_requests_total{method="GET",route="/health_check",status="200"}"#,
+    );
+    assert!(requests.unwrap() >= 1.0);
+    assert!(metrics.contains(
+        r#"zero2prod_http_request_duration_seconds_count{method="GET",route="/health_check",status="200"}"#
+    ));
+    assert!(metric_value(&metrics, r#"zero2prod_db_pool_connections{state="max"}"#).is_some());
+}
+
+#[tokio::test]
+async fn paths_are_labelled_with_their_route_pattern() {
+    let app = spawn_app().await;
+
+    app.api_client
+        .get(format!("{}/a-page-that-does-not-exist", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let metrics = app.get_metrics().await;
+
+    assert!(metrics.contains(r#"route="unmatched",status="404""#));
+    assert!(!metrics.contains("a-page-that-does-not-exist"));
+}
+
+#[tokio::test]
+async fn sent_emails_are_counted() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let metrics = app.get_metrics().await;
+
+    let sent = metric_value(&metrics, r#"zero2prod_emails_sent_total{outcome="sent"}"#);
+    assert!(sent.unwrap() >= 1.0);
+}
+
+#[tokio::test]
+async fn password_verifications_are_timed() {
+    let app = spawn_app().await;
+
+    app.login_test_user().await;
+    let metrics = app.get_metrics().await;
+
+    let verifications = metric_value(
+        &metrics,
+        "zero2prod_password_verification_duration_seconds_count",
+    );
+    assert!(verifications.unwrap() >= 1.0);
+}
+
+#[tokio::test]
+async fn session_store_errors_are_counted() {
+    let app = spawn_app_with(|c| c.session.store = SessionStoreKind::Postgres).await;
+    sqlx::query!("DROP TABLE sessions")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Rendering the login form stores a CSRF token in a new session.
+    app.api_client
+        .get(format!("{}/login", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let metrics = app.get_metrics().await;
+
+    let errors = metric_value(
+        &metrics,
+        r#"zero2prod_session_store_errors_total{operation="save"}"#,
+    );
+    assert!(errors.unwrap() >= 1.0);
+}
+
+#[tokio::test]
+async fn metrics_can_be_served_on_a_separate_port() {
+    let app = spawn_app_with(|c| c.application.metrics_port = Some(0)).await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/metrics", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    assert!(app.metrics_address.is_some());
+    let metrics = app.get_metrics().await;
+    assert!(metrics.contains("zero2prod_db_pool_connections"));
+}
+
+#[tokio::test]
+async fn scrapers_must_present_the_metrics_token() {
+    let app = spawn_app().await;
+
+    let response = app.get_metrics_with(None).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.get_metrics_with(Some("not-the-token")).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn the_separate_port_also_checks_a_configured_token() {
+    let app = spawn_app_with(|c| c.application.metrics_port = Some(0)).await;
+
+    let response = app.get_metrics_with(None).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn metrics_are_not_served_alongside_the_application_without_a_token() {
+    let app = spawn_app_with(|c| c.application.metrics_token = None).await;
+
+    let response = app.get_metrics_with(None).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,185 @@
+//! tests/api/rate_limiting.rs
+use crate::helpers::{spawn_app_with, TestApp};
+use secrecy::Secret;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::configuration::{RateLimit, RateLimitStoreKind};
+
+const TWO_PER_MINUTE: RateLimit = RateLimit {
+    capacity: 2,
+    refill_seconds: 60,
+};
+
+fn assert_is_rate_limited(response: &reqwest::Response) {
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after: u64 = response
+        .headers()
+        .get("Retry-After")
+        .expect("No Retry-After header.")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+}
+
+async fn mock_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn login_attempts_are_rate_limited_per_ip() {
+    let app = spawn_app_with(|c| c.rate_limiting.login = TWO_PER_MINUTE).await;
+    let body = serde_json::json!({
+        "username": "random-username",
+        "password": "random-password"
+    });
+
+    for _ in 0..2 {
+        let response = app.post_login(&body).await;
+        assert_eq!(response.status().as_u16(), 303);
+    }
+    let response = app.post_login(&body).await;
+
+    assert_is_rate_limited(&response);
+    // Rendering the login form is not limited.
+    let response = app
+        .api_client
+        .get(format!("{}/login", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn subscriptions_are_rate_limited_per_ip() {
+    let app = spawn_app_with(|c| c.rate_limiting.subscriptions = TWO_PER_MINUTE).await;
+    mock_email_server(&app).await;
+
+    for i in 0..2 {
+        let body = format!("name=le%20guin&email=ursula{}%40gmail.com", i);
+        let response = app.post_subscriptions(body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let response = app.post_subscriptions(body.into()).await;
+
+    assert_is_rate_limited(&response);
+}
+
+#[tokio::test]
+async fn confirmation_emails_are_rate_limited_per_address() {
+    let app = spawn_app_with(|c| {
+        c.rate_limiting.subscriptions_per_email = RateLimit {
+            capacity: 1,
+            refill_seconds: 3600,
+        }
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let response = app.post_subscriptions(body.into()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Addresses differing in case only are the same mailbox.
+    let body = "name=le%20guin&email=Ursula_Le_Guin%40gmail.com";
+    let response = app.post_subscriptions(body.into()).await;
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after = response.headers().get("Retry-After").unwrap();
+    assert_eq!(retry_after, "3600");
+
+    // Other addresses are unaffected.
+    let body = "name=le%20guin&email=someone_else%40gmail.com";
+    let response = app.post_subscriptions(body.into()).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn subscription_confirmations_are_rate_limited_per_ip() {
+    let app = spawn_app_with(|c| c.rate_limiting.subscriptions_confirm = TWO_PER_MINUTE).await;
+    let url = format!(
+        "{}/subscriptions/confirm?subscription_token=aaaaaaaaaaaaaaaaaaaaaaaaa",
+        app.address
+    );
+
+    for _ in 0..2 {
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    let response = reqwest::get(&url).await.unwrap();
+
+    assert_is_rate_limited(&response);
+}
+
+#[tokio::test]
+async fn only_the_forwarded_address_appended_by_the_proxy_is_trusted() {
+    let app = spawn_app_with(|c| {
+        c.rate_limiting.use_forwarded_headers = true;
+        c.rate_limiting.subscriptions_confirm = TWO_PER_MINUTE;
+    })
+    .await;
+    let url = format!(
+        "{}/subscriptions/confirm?subscription_token=aaaaaaaaaaaaaaaaaaaaaaaaa",
+        app.address
+    );
+    let confirm = |forwarded_for: Option<String>| {
+        let mut request = reqwest::Client::new().get(&url);
+        if let Some(forwarded_for) = forwarded_for {
+            request = request.header("X-Forwarded-For", forwarded_for);
+        }
+        request.send()
+    };
+
+    // The client picks the entries before the proxy's.
+    for i in 0..2 {
+        let response = confirm(Some(format!("10.0.0.{}, 203.0.113.7", i)))
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    let response = confirm(Some("10.0.0.99, 203.0.113.7".into()))
+        .await
+        .unwrap();
+    assert_is_rate_limited(&response);
+
+    // Without the header, clients are keyed by IP, whatever their port.
+    for _ in 0..2 {
+        let response = confirm(None).await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    let response = confirm(None).await.unwrap();
+    assert_is_rate_limited(&response);
+}
+
+#[tokio::test]
+async fn rate_limiting_falls_back_to_memory_when_redis_is_unreachable() {
+    let app = spawn_app_with(|c| {
+        c.rate_limiting.store = RateLimitStoreKind::Redis;
+        c.redis_uri = Some(Secret::new("redis://127.0.0.1:1".into()));
+        c.rate_limiting.login = TWO_PER_MINUTE;
+    })
+    .await;
+    let body = serde_json::json!({
+        "username": "random-username",
+        "password": "random-password"
+    });
+
+    for _ in 0..2 {
+        let response = app.post_login(&body).await;
+        assert_eq!(response.status().as_u16(), 303);
+    }
+    let response = app.post_login(&body).await;
+
+    assert_is_rate_limited(&response);
+}
@@ -0,0 +1,126 @@
+//! tests/api/reauthentication.rs
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use std::time::Duration;
+
+/// An app whose users must re-enter their password after two seconds, with
+/// the test user logged in and the window already past.
+async fn spawn_app_past_the_reauthentication_window() -> TestApp {
+    let app = spawn_app_with(|c| c.session.reauthentication_window_seconds = 2).await;
+    app.login_test_user().await;
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    app
+}
+
+async fn api_token_count(app: &TestApp) -> i64 {
+    sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM api_tokens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn sensitive_pages_are_open_right_after_login() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app.get_newsletter().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn sensitive_pages_require_the_password_once_the_window_has_passed() {
+    let app = spawn_app_past_the_reauthentication_window().await;
+
+    let response = app.get_newsletter().await;
+
+    assert_is_redirect_to(
+        &response,
+        "/admin/reauthenticate?next=%2Fadmin%2Fnewsletters",
+    );
+    let html_page = app.get_reauthenticate_html("/admin/newsletters").await;
+    assert!(html_page.contains("<p><i>Please enter your password again to continue.</i></p>"));
+    assert!(html_page.contains(r#"name="next" value="&#x2F;admin&#x2F;newsletters""#));
+}
+
+#[tokio::test]
+async fn entering_the_password_again_unlocks_the_sensitive_page() {
+    let app = spawn_app_past_the_reauthentication_window().await;
+
+    let response = app
+        .post_reauthenticate(&app.test_user.password, "/admin/newsletters")
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let response = app.get_newsletter().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn a_wrong_password_keeps_the_sensitive_page_locked() {
+    let app = spawn_app_past_the_reauthentication_window().await;
+
+    let response = app
+        .post_reauthenticate("not-the-password", "/admin/newsletters")
+        .await;
+
+    assert_is_redirect_to(
+        &response,
+        "/admin/reauthenticate?next=%2Fadmin%2Fnewsletters",
+    );
+    let html_page = app.get_reauthenticate_html("/admin/newsletters").await;
+    assert!(html_page.contains("<p><i>The password is incorrect.</i></p>"));
+    let response = app.get_newsletter().await;
+    assert_is_redirect_to(
+        &response,
+        "/admin/reauthenticate?next=%2Fadmin%2Fnewsletters",
+    );
+}
+
+#[tokio::test]
+async fn sensitive_form_submissions_are_refused_outside_the_window() {
+    let app = spawn_app_past_the_reauthentication_window().await;
+
+    let body = [
+        ("name", "ci"),
+        ("expires_in_days", "30"),
+        ("scopes", "newsletters:publish"),
+    ];
+    let response = app.post_api_tokens(&body).await;
+
+    assert_is_redirect_to(&response, "/admin/reauthenticate?next=%2Fadmin%2Ftokens");
+    assert_eq!(api_token_count(&app).await, 0);
+    // Listing tokens is not sensitive.
+    assert_eq!(app.get_api_tokens().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn exporting_the_audit_log_requires_a_recent_password() {
+    let app = spawn_app_past_the_reauthentication_window().await;
+
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/admin/audit/export?action=login.succeeded",
+            &app.address
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(
+        &response,
+        "/admin/reauthenticate?next=%2Fadmin%2Faudit%2Fexport%3Faction%3Dlogin.succeeded",
+    );
+}
+
+#[tokio::test]
+async fn users_are_only_sent_back_within_the_admin_area() {
+    let app = spawn_app_past_the_reauthentication_window().await;
+
+    let response = app
+        .post_reauthenticate(&app.test_user.password, "https://example.com/admin/")
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
@@ -0,0 +1,106 @@
+//! tests/api/request_id.rs
+use crate::helpers::{spawn_app, TestApp};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const BODY: &str = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+async fn post_subscriptions_with_request_id(app: &TestApp, request_id: &str) -> reqwest::Response {
+    let body = app.complete_subscribe_form(BODY.into()).await;
+    app.api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("X-Request-Id", request_id)
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn request_id(response: &reqwest::Response) -> &str {
+    response
+        .headers()
+        .get("X-Request-Id")
+        .expect("No X-Request-Id in the response.")
+        .to_str()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn responses_carry_a_generated_request_id() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/health_check", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert!(Uuid::parse_str(request_id(&response)).is_ok());
+}
+
+#[tokio::test]
+async fn the_request_id_sent_by_the_client_is_echoed() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/health_check", &app.address))
+        .header("X-Request-Id", "client-request-42")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(request_id(&response), "client-request-42");
+}
+
+#[tokio::test]
+async fn invalid_request_ids_are_replaced() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/health_check", &app.address))
+        .header("X-Request-Id", "a".repeat(129))
+        .send()
+        .await
+        .unwrap();
+
+    assert!(Uuid::parse_str(request_id(&response)).is_ok());
+}
+
+#[tokio::test]
+async fn emails_carry_the_request_id_as_metadata() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = post_subscriptions_with_request_id(&app, "subscribe-1234").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["Metadata"]["request_id"], "subscribe-1234");
+}
+
+#[tokio::test]
+async fn server_errors_quote_the_request_id() {
+    let app = spawn_app().await;
+    sqlx::query!("ALTER TABLE subscription_tokens DROP COLUMN subscription_token;",)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = post_subscriptions_with_request_id(&app, "broken-5678").await;
+
+    assert_eq!(response.status().as_u16(), 500);
+    assert_eq!(request_id(&response), "broken-5678");
+    let body = response.text().await.unwrap();
+    assert!(body.contains("Request ID: broken-5678"));
+}
@@ -0,0 +1,110 @@
+//! tests/api/runtime_settings.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::configuration::RateLimit;
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_reload_the_settings() {
+    let app = spawn_app().await;
+
+    let response = app.get_runtime_settings().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app.post_reload_settings().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn reloaded_rate_limits_apply_to_the_next_requests() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.configuration
+        .lock()
+        .unwrap()
+        .rate_limiting
+        .subscriptions = RateLimit {
+        capacity: 2,
+        refill_seconds: 60,
+    };
+    let response = app.post_reload_settings().await;
+    assert_is_redirect_to(&response, "/admin/settings");
+
+    let html_page = app.get_runtime_settings_html().await;
+    assert!(html_page.contains("<p><i>Applied: rate_limiting.subscriptions.capacity, rate_limiting.subscriptions.refill_seconds.</i></p>"));
+    assert!(
+        html_page.contains("<td>rate_limiting.subscriptions.capacity</td>\n            <td>2</td>")
+    );
+    for i in 0..2 {
+        let body = format!("name=le%20guin&email=reloaded{}%40gmail.com", i);
+        let response = app.post_subscriptions(body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let body = "name=le%20guin&email=reloaded2%40gmail.com";
+    let response = app.post_subscriptions(body.into()).await;
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn changes_that_need_a_restart_are_reported_and_not_applied() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    {
+        let mut configuration = app.configuration.lock().unwrap();
+        configuration.application.base_url = "https://reloaded.example.com".into();
+        configuration.log_filter = "info,zero2prod=debug".into();
+    }
+    app.post_reload_settings().await;
+
+    let html_page = app.get_runtime_settings_html().await;
+    assert!(html_page.contains("<p><i>Applied: log_filter.</i></p>"));
+    assert!(
+        html_page.contains("<p><i>Restart the application to apply: application.base_url.</i></p>")
+    );
+    let details =
+        sqlx::query!("SELECT details FROM audit_events WHERE action = 'settings.reloaded'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .details;
+    assert_eq!(
+        details,
+        serde_json::json!({
+            "applied": ["log_filter"],
+            "requires_restart": ["application.base_url"],
+        })
+    );
+
+    // Only the applied changes are in use now.
+    app.post_reload_settings().await;
+    let html_page = app.get_runtime_settings_html().await;
+    assert!(!html_page.contains("Applied:"));
+    assert!(html_page.contains("Restart the application to apply: application.base_url."));
+}
+
+#[tokio::test]
+async fn an_invalid_configuration_is_not_applied() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    {
+        let mut configuration = app.configuration.lock().unwrap();
+        configuration.log_filter = "zero2prod=loud".into();
+        configuration.email_client.timeout_milliseconds = 5000;
+    }
+    app.post_reload_settings().await;
+
+    let html_page = app.get_runtime_settings_html().await;
+    assert!(html_page.contains("The configuration was not reloaded."));
+    assert!(html_page.contains("log_filter"));
+    assert!(html_page
+        .contains("<td>email_client.timeout_milliseconds</td>\n            <td>10000</td>"));
+}
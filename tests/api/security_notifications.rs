@@ -0,0 +1,195 @@
+//! tests/api/security_notifications.rs
+use crate::helpers::{assert_is_redirect_to, build_client, extract_csrf_token, spawn_app};
+use std::time::Duration;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// The emails sent so far, once there are at least `count` of them.
+///
+/// Login notifications are sent in the background, after the response.
+async fn sent_emails(app: &crate::helpers::TestApp, count: usize) -> Vec<wiremock::Request> {
+    for _ in 0..100 {
+        let requests = app.email_server.received_requests().await.unwrap();
+        if requests.len() >= count {
+            return requests;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Fewer than {} email(s) were sent.", count);
+}
+
+fn email_body(request: &wiremock::Request) -> serde_json::Value {
+    serde_json::from_slice(&request.body).unwrap()
+}
+
+#[tokio::test]
+async fn logging_in_from_a_new_device_sends_a_notification() {
+    let app = spawn_app().await;
+    let email = app.set_test_user_email().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.login_test_user().await;
+
+    app.login_from_another_device("unfamiliar-browser").await;
+
+    let emails: Vec<_> = sent_emails(&app, 1).await.iter().map(email_body).collect();
+    assert_eq!(emails[0]["To"], email);
+    assert_eq!(emails[0]["Subject"], "New login to your account");
+    let text_body = emails[0]["TextBody"].as_str().unwrap();
+    assert!(text_body.contains("IP address: 127.0.0.1"));
+    assert!(text_body.contains("Device: unfamiliar-browser"));
+    assert!(text_body.contains("/account/revoke-sessions?token="));
+}
+
+#[tokio::test]
+async fn logging_in_from_a_known_device_does_not_send_a_notification() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // The first login of a user is not worth an alert either.
+    app.login_test_user().await;
+    app.post_logout().await;
+    app.login_test_user().await;
+}
+
+#[tokio::test]
+async fn users_without_an_email_address_are_not_notified() {
+    let app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.login_test_user().await;
+
+    app.login_from_another_device("unfamiliar-browser").await;
+}
+
+#[tokio::test]
+async fn logins_succeed_even_if_the_notification_cannot_be_sent() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.login_test_user().await;
+
+    // Asserts the redirect to the dashboard.
+    app.login_from_another_device("unfamiliar-browser").await;
+    sent_emails(&app, 1).await;
+}
+
+#[tokio::test]
+async fn changing_the_password_sends_a_notification() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.login_test_user().await;
+
+    let new_password = "correct horse battery staple";
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": new_password,
+            "new_password_check": new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let emails: Vec<_> = sent_emails(&app, 1).await.iter().map(email_body).collect();
+    assert_eq!(emails[0]["Subject"], "Your password has been changed");
+    let text_body = emails[0]["TextBody"].as_str().unwrap();
+    assert!(text_body.contains("Device: zero2prod-tests"));
+}
+
+#[tokio::test]
+async fn the_notification_link_logs_the_user_out_everywhere() {
+    let app = spawn_app().await;
+    app.set_test_user_email().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.login_test_user().await;
+    let intruder = app.login_from_another_device("unfamiliar-browser").await;
+    let email_request = &sent_emails(&app, 1).await[0];
+    let revocation_link = app.get_confirmation_links(email_request).html;
+
+    // Opening the link does not revoke anything by itself...
+    let victim = build_client("mail-client");
+    let response = victim.get(revocation_link.clone()).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // ...confirming it does.
+    let token = revocation_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .into_owned();
+    let response = victim
+        .post(format!("{}/account/revoke-sessions", app.address))
+        .form(&[
+            ("token", token.as_str()),
+            ("csrf_token", &extract_csrf_token(&html_page)),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("2 session(s) have been logged out."));
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+    let response = intruder
+        .get(format!("{}/admin/dashboard", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+
+    // The link only works once.
+    let response = victim.get(revocation_link).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn an_unknown_revocation_token_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/account/revoke-sessions?token=not-a-real-token",
+            app.address
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,125 @@
+//! tests/api/session_lifetimes.rs
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use std::time::Duration;
+
+async fn login(app: &TestApp, remember_me: bool) -> reqwest::Response {
+    let mut login_body = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password
+    });
+    if remember_me {
+        login_body["remember_me"] = "on".into();
+    }
+    let response = app.post_login(&login_body).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    response
+}
+
+fn session_cookie(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get_all("Set-Cookie")
+        .iter()
+        .map(|value| value.to_str().unwrap().to_owned())
+        .find(|value| value.starts_with("id="))
+        .expect("No session cookie was set.")
+}
+
+#[tokio::test]
+async fn the_login_form_offers_to_remember_the_user() {
+    let app = spawn_app().await;
+
+    let html_page = app.get_login_html().await;
+
+    assert!(html_page.contains(r#"name="remember_me""#));
+}
+
+#[tokio::test]
+async fn sessions_end_with_the_browser_by_default() {
+    let app = spawn_app().await;
+
+    let response = login(&app, false).await;
+
+    let cookie = session_cookie(&response);
+    assert!(!cookie.contains("Max-Age"));
+    assert!(!cookie.contains("Expires"));
+}
+
+#[tokio::test]
+async fn remembered_sessions_get_a_persistent_cookie() {
+    let app = spawn_app_with(|c| c.session.remember_me_seconds = 3600).await;
+
+    let response = login(&app, true).await;
+
+    assert!(session_cookie(&response).contains("Max-Age=3600"));
+}
+
+#[tokio::test]
+async fn idle_sessions_are_expired() {
+    let app = spawn_app_with(|c| c.session.idle_timeout_seconds = 1).await;
+    login(&app, false).await;
+
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    let response = app.get_admin_dashboard().await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("You have been logged out after a period of inactivity."));
+}
+
+#[tokio::test]
+async fn activity_keeps_a_session_alive() {
+    let app = spawn_app_with(|c| c.session.idle_timeout_seconds = 3).await;
+    login(&app, false).await;
+
+    for _ in 0..3 {
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let response = app.get_admin_dashboard().await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn remembered_sessions_outlive_the_idle_timeout() {
+    let app = spawn_app_with(|c| c.session.idle_timeout_seconds = 1).await;
+    login(&app, true).await;
+
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    let response = app.get_admin_dashboard().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn sessions_end_after_the_absolute_timeout() {
+    let app = spawn_app_with(|c| c.session.absolute_timeout_seconds = 1).await;
+    login(&app, false).await;
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let response = app.get_admin_dashboard().await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Your session has expired. Please log in again."));
+    let indexed_sessions = sqlx::query_scalar!(
+        r#"SELECT count(*) AS "count!" FROM user_sessions WHERE user_id = $1"#,
+        app.test_user.user_id,
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(indexed_sessions, 0);
+}
+
+#[tokio::test]
+async fn logging_out_is_not_mistaken_for_inactivity() {
+    let app = spawn_app().await;
+    login(&app, false).await;
+    app.post_logout().await;
+
+    let response = app.get_admin_dashboard().await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(!html_page.contains("inactivity"));
+}
@@ -0,0 +1,85 @@
+//! tests/api/session_stores.rs
+use crate::helpers::{assert_is_redirect_to, spawn_app_with};
+use std::time::Duration;
+use zero2prod::configuration::{get_configuration, SessionStoreKind};
+use zero2prod::startup::Application;
+
+#[tokio::test]
+async fn sessions_can_be_kept_in_postgres() {
+    let app = spawn_app_with(|c| {
+        c.session.store = SessionStoreKind::Postgres;
+        c.redis_uri = None;
+    })
+    .await;
+
+    app.login_test_user().await;
+    let html_page = app.get_admin_dashboard_html().await;
+
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+    let stored_sessions = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM sessions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(stored_sessions, 1);
+}
+
+#[tokio::test]
+async fn logging_out_deletes_the_postgres_session() {
+    let app = spawn_app_with(|c| c.session.store = SessionStoreKind::Postgres).await;
+    app.login_test_user().await;
+
+    let response = app.post_logout().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+    let logged_in_sessions =
+        sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM sessions WHERE state ? 'user_id'"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(logged_in_sessions, 0);
+}
+
+#[tokio::test]
+async fn postgres_sessions_expire_when_idle() {
+    let app = spawn_app_with(|c| {
+        c.session.store = SessionStoreKind::Postgres;
+        c.session.idle_timeout_seconds = 1;
+    })
+    .await;
+    app.login_test_user().await;
+
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    let response = app.get_admin_dashboard().await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("You have been logged out after a period of inactivity."));
+}
+
+#[tokio::test]
+async fn sessions_can_still_be_kept_in_redis() {
+    let app = spawn_app_with(|c| c.session.store = SessionStoreKind::Redis).await;
+
+    app.login_test_user().await;
+    let response = app.get_admin_dashboard().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_redis_store_requires_a_redis_uri() {
+    let mut configuration = get_configuration().unwrap();
+    configuration.application.port = 0;
+    configuration.session.store = SessionStoreKind::Redis;
+    configuration.redis_uri = None;
+
+    let load_settings = Box::new(|| Ok(get_configuration()?));
+    let error = match Application::build(configuration, load_settings, None).await {
+        Ok(_) => panic!("The application started without a Redis URI."),
+        Err(e) => e,
+    };
+
+    assert!(format!("{:?}", error).contains("`redis_uri` must be set"));
+}
@@ -0,0 +1,130 @@
+//! tests/api/sessions.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+
+async fn session_id_for_user_agent(app: &TestApp, user_agent: &str) -> Uuid {
+    sqlx::query!(
+        "SELECT session_id FROM user_sessions WHERE user_agent = $1",
+        user_agent
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .session_id
+}
+
+async fn is_logged_in(app: &TestApp, client: &reqwest::Client) -> bool {
+    client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .is_success()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_your_sessions() {
+    let app = spawn_app().await;
+
+    let response = app.get_sessions().await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn active_sessions_are_listed_with_their_metadata() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    app.login_from_another_device("Laptop browser").await;
+
+    let html_page = app.get_sessions_html().await;
+
+    assert!(html_page.contains("<td>zero2prod-tests</td>"));
+    assert!(html_page.contains("<td>Laptop browser</td>"));
+    assert!(html_page.contains("<td>127.0.0.1</td>"));
+    assert!(html_page.contains("This session"));
+}
+
+#[tokio::test]
+async fn a_revoked_session_is_logged_out() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let other_device = app.login_from_another_device("Stolen laptop").await;
+    assert!(is_logged_in(&app, &other_device).await);
+
+    let session_id = session_id_for_user_agent(&app, "Stolen laptop").await;
+    let response = app.post_revoke_session(session_id).await;
+    assert_is_redirect_to(&response, "/admin/sessions");
+
+    let html_page = app.get_sessions_html().await;
+    assert!(html_page.contains("<p><i>The session has been revoked.</i></p>"));
+    assert!(!html_page.contains("Stolen laptop"));
+    assert!(!is_logged_in(&app, &other_device).await);
+    assert!(is_logged_in(&app, &app.api_client).await);
+}
+
+#[tokio::test]
+async fn the_current_session_cannot_be_revoked_from_the_list() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let session_id = session_id_for_user_agent(&app, "zero2prod-tests").await;
+    let response = app.post_revoke_session(session_id).await;
+    assert_is_redirect_to(&response, "/admin/sessions");
+
+    let html_page = app.get_sessions_html().await;
+    assert!(html_page.contains("<p><i>Use the logout button to end the current session.</i></p>"));
+}
+
+#[tokio::test]
+async fn all_other_sessions_can_be_revoked_at_once() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let laptop = app.login_from_another_device("Laptop").await;
+    let phone = app.login_from_another_device("Phone").await;
+
+    let response = app.post_revoke_other_sessions().await;
+    assert_is_redirect_to(&response, "/admin/sessions");
+
+    let html_page = app.get_sessions_html().await;
+    assert!(html_page.contains("<p><i>2 other session(s) have been revoked.</i></p>"));
+    assert!(!is_logged_in(&app, &laptop).await);
+    assert!(!is_logged_in(&app, &phone).await);
+    assert!(is_logged_in(&app, &app.api_client).await);
+}
+
+#[tokio::test]
+async fn changing_password_logs_out_every_other_session() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let other_device = app.login_from_another_device("Laptop").await;
+    let new_password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    assert!(!is_logged_in(&app, &other_device).await);
+    assert!(is_logged_in(&app, &app.api_client).await);
+}
+
+#[tokio::test]
+async fn logging_out_removes_the_session_from_the_list() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    app.post_logout().await;
+
+    let remaining = sqlx::query!("SELECT session_id FROM user_sessions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(remaining.is_empty());
+}
@@ -0,0 +1,171 @@
+//! tests/api/setup.rs
+
+use crate::helpers::{
+    assert_is_redirect_to, post_form, spawn_app, spawn_app_without_users, TestApp,
+};
+use secrecy::Secret;
+use zero2prod::configuration::BootstrapOwnerSettings;
+
+async fn get_setup(app: &TestApp, token: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/setup", &app.address))
+        .query(&[("token", token)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn post_setup(app: &TestApp, token: &str, password_check: &str) -> reqwest::Response {
+    let body = serde_json::json!({
+        "setup_token": token,
+        "username": &app.test_user.username,
+        "password": &app.test_user.password,
+        "password_check": password_check,
+    });
+    post_form(&app.api_client, &app.address, "/setup", &body).await
+}
+
+async fn user_roles(app: &TestApp) -> Vec<(String, String)> {
+    sqlx::query!("SELECT username, role FROM users WHERE disabled_at IS NULL")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.username, r.role))
+        .collect()
+}
+
+#[tokio::test]
+async fn the_seed_admin_is_not_created_anymore() {
+    let app = spawn_app_without_users(|_| {}).await;
+
+    assert!(user_roles(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn the_first_owner_is_created_through_the_setup_page() {
+    let app = spawn_app_without_users(|_| {}).await;
+    let token = app
+        .setup_token
+        .clone()
+        .expect("No setup token was generated.");
+
+    let response = get_setup(&app, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Create the owner account"));
+
+    let response = post_setup(&app, &token, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("<p><i>The owner account has been created. You can now log in.</i></p>"));
+    assert_eq!(
+        user_roles(&app).await,
+        vec![(app.test_user.username.clone(), "owner".to_string())]
+    );
+    app.login_test_user().await;
+}
+
+#[tokio::test]
+async fn the_setup_page_is_gone_once_a_user_exists() {
+    let app = spawn_app().await;
+    let token = app
+        .setup_token
+        .clone()
+        .expect("No setup token was generated.");
+
+    assert_is_redirect_to(&get_setup(&app, &token).await, "/login");
+
+    let response = post_setup(&app, &token, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("<p><i>Setup has already been completed.</i></p>"));
+    assert_eq!(user_roles(&app).await.len(), 1);
+}
+
+#[tokio::test]
+async fn the_setup_page_requires_the_logged_token() {
+    let app = spawn_app_without_users(|_| {}).await;
+
+    assert_eq!(
+        get_setup(&app, "not-the-token").await.status().as_u16(),
+        403
+    );
+    let response = post_setup(&app, "not-the-token", &app.test_user.password).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(user_roles(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn the_password_must_be_typed_twice() {
+    let app = spawn_app_without_users(|_| {}).await;
+    let token = app.setup_token.clone().unwrap();
+
+    let response = post_setup(&app, &token, "something-else-entirely").await;
+
+    assert_is_redirect_to(&response, &format!("/setup?token={}", token));
+    assert!(get_setup(&app, &token)
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains("You entered two different passwords"));
+    assert!(user_roles(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn a_guessable_password_is_refused() {
+    let app = spawn_app_without_users(|_| {}).await;
+    let token = app.setup_token.clone().unwrap();
+    let body = serde_json::json!({
+        "setup_token": &token,
+        "username": "owner",
+        "password": "zzzzzzzzzzzzzzzz",
+        "password_check": "zzzzzzzzzzzzzzzz",
+    });
+
+    let response = post_form(&app.api_client, &app.address, "/setup", &body).await;
+
+    assert_is_redirect_to(&response, &format!("/setup?token={}", token));
+    assert!(get_setup(&app, &token)
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains("This password is too easy to guess."));
+    assert!(user_roles(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn a_configured_owner_is_created_on_startup() {
+    let app = spawn_app_without_users(|configuration| {
+        configuration.bootstrap_owner = Some(BootstrapOwnerSettings {
+            username: "founder".into(),
+            password: Secret::new("a-long-enough-password".into()),
+            email: None,
+        });
+    })
+    .await;
+
+    assert!(app.setup_token.is_none());
+    assert_eq!(
+        user_roles(&app).await,
+        vec![("founder".to_string(), "owner".to_string())]
+    );
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "founder",
+            "password": "a-long-enough-password",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let response = get_setup(&app, "anything").await;
+    assert_eq!(response.status().as_u16(), 404);
+}
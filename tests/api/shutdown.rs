@@ -0,0 +1,63 @@
+//! tests/api/shutdown.rs
+use crate::helpers::spawn_app;
+use std::time::Duration;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn shutting_down_lets_in_flight_requests_complete() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(1)))
+        .mount(&app.email_server)
+        .await;
+
+    // Act - shut down while the confirmation email is being sent.
+    // Mock servers are reused across tests, so wait for this test's email
+    // rather than for any request.
+    let email = format!("{}@example.com", Uuid::new_v4());
+    let trigger_shutdown = async {
+        loop {
+            let requests = app.email_server.received_requests().await.unwrap();
+            if requests
+                .iter()
+                .any(|request| String::from_utf8_lossy(&request.body).contains(&email))
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        app.shutdown.cancel();
+    };
+    let body = format!("name=le%20guin&email={}", urlencoding::encode(&email));
+    let (response, _) = tokio::join!(app.post_subscriptions(body), trigger_shutdown);
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.email, email);
+}
+
+#[tokio::test]
+async fn the_server_stops_accepting_connections_after_shutting_down() {
+    let app = spawn_app().await;
+
+    app.shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), app.server)
+        .await
+        .expect("The server did not stop in time.")
+        .unwrap()
+        .unwrap();
+
+    let outcome = reqwest::Client::new()
+        .get(format!("{}/health/live", &app.address))
+        .send()
+        .await;
+    assert!(outcome.is_err());
+}
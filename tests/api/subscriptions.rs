@@ -1,9 +1,9 @@
 //! tests/api/subscriptions.rs
 
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, spawn_app_with};
 
 #[tokio::test]
 async fn subscribe_returns_200_for_valid_form_data() {
@@ -168,3 +168,135 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error_to_subscriptions() {
     let response = app.post_subscriptions(body.into()).await;
     assert_eq!(500, response.status().as_u16());
 }
+
+#[tokio::test]
+async fn the_subscribe_form_embeds_a_form_token_and_a_honeypot() {
+    let app = spawn_app().await;
+
+    let html_page = app.get_subscribe_form_html().await;
+
+    assert!(html_page.contains(r#"name="form_token" value=""#));
+    assert!(html_page.contains(r#"name="website""#));
+}
+
+#[tokio::test]
+async fn subscribe_ignores_submissions_with_the_honeypot_filled_in() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&website=https%3A%2F%2Fspam.example";
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions(body.into()).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let subscribers = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(subscribers.is_empty());
+}
+
+#[tokio::test]
+async fn subscribe_rejects_submissions_without_a_valid_form_token() {
+    let app = spawn_app().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let test_cases = vec![
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com",
+            "no form token",
+        ),
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&form_token=1700000000.abcdef",
+            "a forged form token",
+        ),
+    ];
+    for (body, description) in test_cases {
+        let response = app.post_raw_subscriptions(body.into()).await;
+
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 when the payload had {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribe_rejects_forms_submitted_too_quickly() {
+    let app = spawn_app_with(|c| c.bot_protection.min_fill_seconds = 60).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions(body.into()).await;
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn subscribe_requires_the_proof_of_work_when_enabled() {
+    let app = spawn_app_with(|c| c.bot_protection.proof_of_work_difficulty = 8).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let html_page = app.get_subscribe_form_html().await;
+    assert!(html_page.contains(r#"data-difficulty="8""#));
+    let form_token = html_page
+        .split(r#"name="form_token" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    let body = format!(
+        "name=le%20guin&email=ursula_le_guin%40gmail.com&form_token={}",
+        form_token
+    );
+    let response = app.post_raw_subscriptions(body).await;
+    assert_eq!(400, response.status().as_u16());
+
+    // The helper solves the challenge, as the page's script would.
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let response = app.post_subscriptions(body.into()).await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn a_solved_proof_of_work_cannot_be_replayed_for_another_address() {
+    let app = spawn_app_with(|c| c.bot_protection.proof_of_work_difficulty = 8).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = app
+        .complete_subscribe_form("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let response = app.post_raw_subscriptions(body.clone()).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let replayed = body.replace("ursula_le_guin", "another_victim");
+    let response = app.post_raw_subscriptions(replayed).await;
+    assert_eq!(400, response.status().as_u16());
+}
@@ -0,0 +1,137 @@
+//! tests/telemetry.rs
+//!
+//! Trace export gets a test binary of its own, as it needs the process-wide
+//! subscriber to carry an OpenTelemetry layer.
+
+use secrecy::Secret;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use zero2prod::configuration::{
+    get_configuration, DatabaseSettings, OpenTelemetrySettings, RateLimitStoreKind,
+    SessionStoreKind,
+};
+use zero2prod::startup::Application;
+use zero2prod::telemetry::{get_subscriber, get_tracer_provider, init_subscriber};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+#[tokio::test]
+async fn traces_are_exported_and_propagated_to_the_email_provider() {
+    // Arrange
+    let collector = MockServer::start().await;
+    Mock::given(path("/v1/traces"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&collector)
+        .await;
+    let tracer_provider = get_tracer_provider(
+        "test".into(),
+        &OpenTelemetrySettings {
+            endpoint: format!("{}/v1/traces", collector.uri()),
+        },
+    )
+    .unwrap();
+    let (subscriber, _) = get_subscriber(
+        "test".into(),
+        "info".into(),
+        std::io::sink,
+        Some(&tracer_provider),
+    );
+    init_subscriber(subscriber);
+
+    let email_server = MockServer::start().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&email_server)
+        .await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = Uuid::new_v4().to_string();
+    configuration.application.port = 0;
+    configuration.email_client.base_url = email_server.uri();
+    configuration.session.store = SessionStoreKind::Memory;
+    configuration.bot_protection.min_fill_seconds = 0;
+    configuration.rate_limiting.store = RateLimitStoreKind::Memory;
+    configure_database(&configuration.database).await;
+    let load_settings = Box::new(|| Ok(get_configuration()?));
+    let application = Application::build(configuration, load_settings, None)
+        .await
+        .expect("Failed to build application");
+    let address = format!("http://127.0.0.1:{}", application.port());
+    std::mem::drop(tokio::spawn(application.run_until_stopped()));
+    let client = reqwest::Client::new();
+
+    let form = client
+        .get(format!("{}/subscriptions", address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let marker = r#"name="form_token" value=""#;
+    let start = form.find(marker).unwrap() + marker.len();
+    let form_token = &form[start..start + form[start..].find('"').unwrap()];
+
+    // Act
+    let response = client
+        .post(format!("{}/subscriptions", address))
+        .header(
+            "traceparent",
+            format!("00-{}-00f067aa0ba902b7-01", TRACE_ID),
+        )
+        .form(&[
+            ("name", "le guin"),
+            ("email", "ursula_le_guin@gmail.com"),
+            ("form_token", form_token),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Assert
+    let email_request = &email_server.received_requests().await.unwrap()[0];
+    let traceparent = email_request.headers.get("traceparent").unwrap();
+    assert!(traceparent
+        .to_str()
+        .unwrap()
+        .starts_with(&format!("00-{}-", TRACE_ID)));
+
+    let flushed_provider = tracer_provider.clone();
+    tokio::task::spawn_blocking(move || flushed_provider.force_flush())
+        .await
+        .unwrap()
+        .unwrap();
+    let trace_id = hex::decode(TRACE_ID).unwrap();
+    let exports = collector.received_requests().await.unwrap();
+    assert!(exports
+        .iter()
+        .any(|export| export.body.windows(trace_id.len()).any(|w| w == trace_id)));
+}
+
+async fn configure_database(config: &DatabaseSettings) {
+    let maintenance_settings = DatabaseSettings {
+        database_name: "postgres".to_string(),
+        username: "postgres".to_string(),
+        password: Secret::new("password".to_string()),
+        ..config.clone()
+    };
+    let mut connection = PgConnection::connect_with(&maintenance_settings.connection_options())
+        .await
+        .expect("Failed to connect to Postgres");
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}";"#, config.database_name).as_str())
+        .await
+        .expect("Failed to create database.");
+
+    let connection_pool = PgPool::connect_with(config.connection_options())
+        .await
+        .expect("Failed to connect to Postgres.");
+    sqlx::migrate!("./migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+}